@@ -0,0 +1,80 @@
+//! Compares GPU throughput with one kernel launch in flight against two, where the results of
+//! one launch are read back while the device works on the next.
+//!
+//! Run with `cargo bench --bench gpu_throughput`. Uses the default OpenCL device with 1M-salt
+//! launches and skips if there is none.
+
+use erc6551crunch::{Batch, Config, OpenClBackend, SearchBackend, select_devices};
+use std::time::Instant;
+
+const BATCHES: u64 = 64;
+
+fn main() {
+    let args = [
+        "gpu_throughput",
+        "-i",
+        "0x55266d75D1a14E4572138116aF39863Ed6596E7F",
+        "-c",
+        "1",
+        "-n",
+        "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D",
+        "-t",
+        "1",
+        "-p",
+        "00000000",
+        "--no-file",
+        "--gpu",
+        "--launch-size",
+        "1048576",
+        "--local-size",
+        "0",
+    ];
+    let config = Config::new(args.into_iter().map(String::from)).unwrap();
+    let targets = &config.targets;
+
+    let device = match select_devices(None, &[]) {
+        Ok(devices) if !devices.is_empty() => devices[0],
+        Ok(_) => return println!("skipping: no OpenCL device"),
+        Err(err) => return println!("skipping: {err}"),
+    };
+
+    let mut baseline = None;
+    for launches in [1, 2] {
+        let mut backend = OpenClBackend::new(device.0, device.1).with_launches_in_flight(launches);
+        if let Err(err) = backend.prepare(&config, targets) {
+            return println!("skipping: {err}");
+        }
+        let batch_size = backend.batch_size();
+        let mut batch = Batch {
+            salt_base: config.salt_template.with_segment(&[0; 6]),
+            counters: 0..batch_size,
+            targets: 0..targets.len(),
+            min_score: 0,
+            first_only: false,
+        };
+
+        // The first batch warms the device up and isn't timed
+        backend.run_batch(&config, &batch, &|| false).unwrap();
+        let start = Instant::now();
+        let mut checked = 0;
+        for _ in 0..BATCHES {
+            batch.counters = batch.counters.end..batch.counters.end + batch_size;
+            checked += backend
+                .run_batch(&config, &batch, &|| false)
+                .unwrap()
+                .checked;
+        }
+        let speed = checked as f64 / start.elapsed().as_secs_f64() / 1_000_000.0;
+
+        match baseline {
+            None => {
+                println!("{launches} launch in flight:  {speed:.2}M/s");
+                baseline = Some(speed);
+            }
+            Some(baseline) => println!(
+                "{launches} launches in flight: {speed:.2}M/s ({:+.1}%)",
+                (speed / baseline - 1.0) * 100.0
+            ),
+        }
+    }
+}
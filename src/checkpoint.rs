@@ -0,0 +1,370 @@
+//! Deterministic salt ordering and `--checkpoint` files, so runs can be reproduced and resumed.
+//!
+//! The searched bytes of a [`SaltTemplate`](crate::SaltTemplate) are `segment || counter (6,
+//! little endian)`. Segment `i` is the `i`-th output of a splitmix64 generator seeded with
+//! `--seed`, and each segment's counter is scanned upwards in batches. Everything before the
+//! cursor has been scanned, except that a batch stopped part-way resumes after its last
+//! reported result, skipping any salts below it that weren't hashed yet. In collection mode
+//! every token is searched from the start of the sequence, in order, until it has a result.
+
+use alloy_primitives::{FixedBytes, Keccak256, keccak256};
+use std::error::Error;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{Config, MAX_INCREMENTER, write_atomically};
+
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Where a search is in the salt space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchPosition {
+    pub seed: u64,
+    /// Index of the token being searched in collection mode, `0` otherwise.
+    pub token: u64,
+    pub segment: u64,
+    /// First counter in `segment` that has not been scanned yet.
+    pub counter: u64,
+}
+
+impl SearchPosition {
+    /// The salt bytes before the counter, truncated when the template searches fewer than 12.
+    pub fn segment_bytes(&self) -> [u8; 6] {
+        let mut z = self.seed.wrapping_add(
+            self.segment
+                .wrapping_add(1)
+                .wrapping_mul(0x9e3779b97f4a7c15),
+        );
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        z.to_le_bytes()[..6].try_into().unwrap()
+    }
+}
+
+/// Hands out batches of salts in order and records how far the search got.
+pub(crate) struct SaltCursor {
+    position: SearchPosition,
+    /// Best score reported so far in optimize mode, so a resumed run only reports better ones.
+    best_score: u32,
+    checkpoint: Option<PathBuf>,
+    search_id: String,
+    last_saved: Instant,
+}
+
+impl SaltCursor {
+    /// Resumes from `--checkpoint` if the file exists, otherwise starts at the beginning of the
+    /// `--seed` (or a random seed's) salt sequence. Prints where it starts unless `quiet`.
+    pub fn open(config: &Config, quiet: bool) -> Result<Self, Box<dyn Error>> {
+        let search_id = search_id(config);
+        let (saved, best_score) = match &config.checkpoint {
+            Some(path) if path.exists() => {
+                let (position, best_score) = read_checkpoint(path, &search_id)?;
+                (Some(position), best_score)
+            }
+            _ => (None, 0),
+        };
+        let position = match (saved, config.seed) {
+            (Some(saved), Some(seed)) if saved.seed != seed => {
+                return Err(format!(
+                    "--seed {:#x} does not match the seed {:#x} in the checkpoint",
+                    seed, saved.seed
+                )
+                .into());
+            }
+            (Some(saved), _) if quiet => saved,
+            (Some(saved), _) => {
+                if config.step_targets(saved.token as usize).is_none() {
+                    println!("♻️  Resuming from checkpoint: every token already has a result");
+                } else if config.optimize {
+                    println!(
+                        "♻️  Resuming from checkpoint: segment {}, counter {}, best score {}",
+                        saved.segment, saved.counter, best_score
+                    );
+                } else if config.collection.is_empty() {
+                    println!(
+                        "♻️  Resuming from checkpoint: segment {}, counter {}",
+                        saved.segment, saved.counter
+                    );
+                } else {
+                    println!(
+                        "♻️  Resuming from checkpoint: token {} of {}, segment {}, counter {}",
+                        saved.token + 1,
+                        config.collection.len(),
+                        saved.segment,
+                        saved.counter
+                    );
+                }
+                saved
+            }
+            (None, seed) => SearchPosition {
+                seed: seed.unwrap_or_else(|| u64::from_le_bytes(FixedBytes::<8>::random().0)),
+                token: 0,
+                segment: 0,
+                counter: 0,
+            },
+        };
+        if !quiet {
+            println!("🎲 Seed: {:#018x}", position.seed);
+            if let Some(path) = &config.checkpoint {
+                println!("💾 Checkpointing to: {}", path.display());
+            }
+        }
+        Ok(Self {
+            position,
+            best_score,
+            checkpoint: config.checkpoint.clone(),
+            search_id,
+            last_saved: Instant::now(),
+        })
+    }
+
+    /// Index of the token being searched in collection mode, `0` otherwise.
+    pub fn token(&self) -> usize {
+        self.position.token as usize
+    }
+
+    /// Best score reported so far in optimize mode, `0` before the first result.
+    pub fn best_score(&self) -> u32 {
+        self.best_score
+    }
+
+    /// Records the best score reported so far, saved with the next checkpoint.
+    pub fn set_best_score(&mut self, best_score: u32) {
+        self.best_score = best_score;
+    }
+
+    /// Moves on to the next token, starting its search from the beginning of the sequence.
+    pub fn next_token(&mut self) -> Result<(), Box<dyn Error>> {
+        self.position.token += 1;
+        self.position.segment = 0;
+        self.position.counter = 0;
+        if self.last_saved.elapsed() >= CHECKPOINT_INTERVAL {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// The next batch of up to `size` counters, and the segment bytes they belong to.
+    pub fn next_batch(&self, size: u64) -> ([u8; 6], Range<u64>) {
+        let start = self.position.counter;
+        let end = start.saturating_add(size).min(MAX_INCREMENTER);
+        (self.position.segment_bytes(), start..end)
+    }
+
+    /// Marks every counter below `end` as scanned, moving on to the next segment once this one
+    /// is exhausted. Saves the checkpoint every few seconds.
+    pub fn complete(&mut self, end: u64) -> Result<(), Box<dyn Error>> {
+        self.position.counter = end;
+        if end >= MAX_INCREMENTER {
+            self.position.segment += 1;
+            self.position.counter = 0;
+        }
+        if self.last_saved.elapsed() >= CHECKPOINT_INTERVAL {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the checkpoint, replacing the old one atomically.
+    pub fn save(&mut self) -> Result<(), Box<dyn Error>> {
+        self.last_saved = Instant::now();
+        let Some(path) = &self.checkpoint else {
+            return Ok(());
+        };
+        let contents = format!(
+            "# erc6551crunch checkpoint: all salts before `counter` in `segment`, and in every\n\
+             # earlier segment, have been scanned for `token`; earlier tokens have a result, and\n\
+             # `best` is the best score reported in optimize mode\n\
+             search={}\nseed={:#018x}\ntoken={}\nsegment={}\ncounter={}\nbest={}\n",
+            self.search_id,
+            self.position.seed,
+            self.position.token,
+            self.position.segment,
+            self.position.counter,
+            self.best_score
+        );
+        write_atomically(path, &contents)
+            .map_err(|err| format!("Could not write checkpoint `{}`: {}", path.display(), err))?;
+        Ok(())
+    }
+}
+
+/// Identifies what a checkpoint was scanning for, so it isn't resumed with different targets.
+fn search_id(config: &Config) -> String {
+    let mut search = format!(
+        "{:?}{:?}",
+        config.resistry_address, config.implement_address
+    );
+    for target in &config.targets {
+        search += &format!(
+            "{:?}{:?}{:?}",
+            target.chain_id, target.nft_address, target.token_id
+        );
+    }
+    search += &format!("{}{}", config.case_sensitive, config.salt_template);
+    // Only added in optimize mode, so checkpoints of other searches stay valid
+    if config.optimize {
+        search += "|optimize";
+    }
+    for pattern in &config.patterns {
+        search += &format!("|{}", pattern);
+    }
+    if !config.collection.is_empty() {
+        let mut collection = Keccak256::new();
+        for token_id in &config.collection {
+            collection.update(token_id);
+        }
+        search += &format!("|{}", collection.finalize());
+    }
+    keccak256(search).to_string()
+}
+
+/// The position saved in the checkpoint, and the best score reported in optimize mode.
+fn read_checkpoint(path: &Path, search_id: &str) -> Result<(SearchPosition, u32), Box<dyn Error>> {
+    let invalid = || format!("`{}` is not a valid checkpoint file", path.display());
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read checkpoint `{}`: {}", path.display(), err))?;
+
+    let (mut search, mut seed, mut segment, mut counter) = (None, None, None, None);
+    let mut token = 0; // not written before collection mode existed
+    let mut best_score = 0; // not written before optimize runs could be resumed
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+        match key {
+            "search" => search = Some(value.to_string()),
+            "seed" => {
+                let digits = value.strip_prefix("0x").ok_or_else(invalid)?;
+                seed = Some(u64::from_str_radix(digits, 16).map_err(|_| invalid())?);
+            }
+            "token" => token = value.parse().map_err(|_| invalid())?,
+            "segment" => segment = Some(value.parse().map_err(|_| invalid())?),
+            "counter" => counter = Some(value.parse().map_err(|_| invalid())?),
+            "best" => best_score = value.parse().map_err(|_| invalid())?,
+            _ => return Err(invalid().into()),
+        }
+    }
+    if search.ok_or_else(invalid)? != search_id {
+        return Err(format!(
+            "Checkpoint `{}` was written for a different search (registry, implementation, \
+             targets, tokens, salt layout, patterns or --optimize changed)",
+            path.display()
+        )
+        .into());
+    }
+    let position = SearchPosition {
+        seed: seed.ok_or_else(invalid)?,
+        token,
+        segment: segment.ok_or_else(invalid)?,
+        counter: counter.ok_or_else(invalid)?,
+    };
+    Ok((position, best_score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config checkpointing to a fresh file named after `test`, with extra `args`.
+    fn config(test: &str, args: &[&str]) -> Config {
+        let path = std::env::temp_dir().join(format!(
+            "erc6551crunch-{}-{}.ckpt",
+            test,
+            std::process::id()
+        ));
+        let mut all = vec![
+            "erc6551crunch",
+            "-i",
+            "0x55266d75D1a14E4572138116aF39863Ed6596E7F",
+            "-c",
+            "1",
+            "-n",
+            "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D",
+            "-t",
+            "1",
+            "--checkpoint",
+            path.to_str().unwrap(),
+        ];
+        all.extend_from_slice(args);
+        Config::new(all.into_iter().map(String::from)).unwrap()
+    }
+
+    fn remove_checkpoint(config: &Config) {
+        let _ = std::fs::remove_file(config.checkpoint.as_ref().unwrap());
+    }
+
+    #[test]
+    fn saved_checkpoints_resume_where_they_left_off() {
+        let config = config("round-trip", &["-p", "dead", "--seed", "0x2a"]);
+        remove_checkpoint(&config);
+
+        let mut cursor = SaltCursor::open(&config, true).unwrap();
+        cursor.complete(MAX_INCREMENTER).unwrap(); // finishes segment 0
+        cursor.complete(1234).unwrap();
+        cursor.save().unwrap();
+
+        let resumed = SaltCursor::open(&config, true).unwrap();
+        assert_eq!(
+            resumed.position,
+            SearchPosition {
+                seed: 0x2a,
+                token: 0,
+                segment: 1,
+                counter: 1234,
+            }
+        );
+        let path = config.checkpoint.as_ref().unwrap();
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        assert!(!Path::new(&temp_path).exists());
+        remove_checkpoint(&config);
+    }
+
+    #[test]
+    fn checkpoints_of_other_searches_are_refused() {
+        let config = config("mismatch", &["-p", "dead", "--seed", "7"]);
+        remove_checkpoint(&config);
+        SaltCursor::open(&config, true).unwrap().save().unwrap();
+
+        let other_pattern = self::config("mismatch", &["-p", "beef", "--seed", "7"]);
+        let err = SaltCursor::open(&other_pattern, true).err().unwrap();
+        assert!(err.to_string().contains("different search"), "{err}");
+
+        let other_seed = self::config("mismatch", &["-p", "dead", "--seed", "8"]);
+        let err = SaltCursor::open(&other_seed, true).err().unwrap();
+        assert!(err.to_string().contains("does not match the seed"), "{err}");
+        remove_checkpoint(&config);
+    }
+
+    #[test]
+    fn optimize_checkpoints_keep_the_best_score() {
+        let config = config("optimize", &["--optimize", "leading-zeros", "--seed", "3"]);
+        remove_checkpoint(&config);
+
+        let mut cursor = SaltCursor::open(&config, true).unwrap();
+        assert_eq!(cursor.best_score(), 0);
+        cursor.set_best_score(4);
+        cursor.save().unwrap();
+        assert_eq!(SaltCursor::open(&config, true).unwrap().best_score(), 4);
+
+        // The same pattern without --optimize reports every match, so it is another search
+        let scored = self::config("optimize", &["--leading-zero-bytes", "1", "--seed", "3"]);
+        let err = SaltCursor::open(&scored, true).err().unwrap();
+        assert!(err.to_string().contains("different search"), "{err}");
+        remove_checkpoint(&config);
+    }
+
+    #[test]
+    fn invalid_checkpoints_are_refused() {
+        let config = config("invalid", &["-p", "dead"]);
+        std::fs::write(config.checkpoint.as_ref().unwrap(), "segment=banana\n").unwrap();
+        let err = SaltCursor::open(&config, true).err().unwrap();
+        assert!(err.to_string().contains("not a valid checkpoint"), "{err}");
+        remove_checkpoint(&config);
+    }
+}
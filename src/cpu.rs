@@ -0,0 +1,140 @@
+//! Searching on the CPU with a rayon thread pool.
+
+use rayon::prelude::*;
+use std::error::Error;
+use std::ops::ControlFlow;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::midstate::{Midstate, address_bytes, hash_bytes};
+use crate::search::{Batch, BatchOutcome, Candidate, SearchBackend};
+use crate::{Config, Target, bytecode_footer, bytecode_header, first_match};
+
+const CPU_BATCH_SIZE: u64 = 1 << 20;
+
+/// Hashes salts on every thread of the global rayon pool.
+#[derive(Debug)]
+pub struct CpuBackend {
+    num_threads: usize, // 0 for rayon's default until prepared
+    bytecode_header: [u8; 55],
+    bytecode_footers: Vec<[u8; 96]>,
+}
+
+impl CpuBackend {
+    /// A backend using `num_threads` threads, or one per core if `0`.
+    pub fn new(num_threads: usize) -> Self {
+        Self {
+            num_threads,
+            bytecode_header: [0; 55],
+            bytecode_footers: Vec::new(),
+        }
+    }
+}
+
+impl SearchBackend for CpuBackend {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
+
+    fn speed_label(&self) -> &'static str {
+        "⚡ Speed"
+    }
+
+    fn batch_size(&self) -> u64 {
+        CPU_BATCH_SIZE
+    }
+
+    fn prepare(&mut self, config: &Config, targets: &[Target]) -> Result<(), Box<dyn Error>> {
+        // Set thread pool size
+        let num_threads = if self.num_threads > 0 {
+            self.num_threads
+        } else {
+            rayon::current_num_threads()
+        };
+
+        if self.num_threads > 0 {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build_global()
+                .ok(); // Ignore error if already initialized
+        }
+
+        self.num_threads = num_threads;
+        self.bytecode_header = bytecode_header(&config.implement_address);
+        self.bytecode_footers = targets.iter().map(bytecode_footer).collect();
+        Ok(())
+    }
+
+    fn describe(&self) -> Vec<String> {
+        vec![format!("🧵 Using {} threads", self.num_threads)]
+    }
+
+    fn run_batch(
+        &mut self,
+        config: &Config,
+        batch: &Batch,
+        stop: &(dyn Fn() -> bool + Sync),
+    ) -> Result<BatchOutcome, Box<dyn Error>> {
+        // Everything but the counter is packed into the hashes' blocks once per batch
+        let midstate = Midstate::new(
+            &config.resistry_address,
+            &self.bytecode_header,
+            &self.bytecode_footers[batch.targets.clone()],
+            &batch.salt_base,
+            config.salt_template.counter_offset(),
+        );
+
+        let candidates = Mutex::new(Vec::new());
+        let checked = AtomicU64::new(0);
+        // Workers skip scores below the best seen in this batch in optimize mode, and salts past
+        // the first match in collection mode
+        let best_score = AtomicU32::new(batch.min_score);
+        let first_counter = AtomicU64::new(u64::MAX);
+
+        let _ = batch
+            .counters
+            .clone()
+            .into_par_iter()
+            .try_for_each(|counter_value| {
+                if batch.first_only && counter_value > first_counter.load(Ordering::Relaxed) {
+                    return ControlFlow::Continue(());
+                }
+                // Workers check the stop flag so a timeout or interrupt ends the batch promptly
+                if stop() {
+                    return ControlFlow::Break(());
+                }
+                checked.fetch_add(1, Ordering::Relaxed);
+
+                for target in batch.targets.clone() {
+                    let init_code_hash =
+                        midstate.init_code_hash(target - batch.targets.start, counter_value);
+                    let address = address_bytes(&midstate.address(counter_value, &init_code_hash));
+
+                    let Some((_, score)) = first_match(config, &address) else {
+                        continue;
+                    };
+                    if config.optimize && best_score.fetch_max(score, Ordering::Relaxed) > score {
+                        continue;
+                    }
+
+                    // Pattern matched!
+                    if batch.first_only {
+                        first_counter.fetch_min(counter_value, Ordering::Relaxed);
+                    }
+                    candidates.lock().unwrap().push(Candidate {
+                        counter: counter_value,
+                        target,
+                        address,
+                        init_code_hash: hash_bytes(&init_code_hash),
+                    });
+                }
+                ControlFlow::Continue(())
+            });
+
+        Ok(BatchOutcome {
+            checked: checked.into_inner(),
+            candidates: candidates.into_inner().unwrap(),
+            overflowed: 0,
+        })
+    }
+}
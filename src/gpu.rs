@@ -1,16 +1,19 @@
-use alloy_primitives::{hex, FixedBytes};
-use ocl::enums::DeviceInfo;
-use ocl::{Buffer, Context, Device, Kernel, Platform, Program, Queue};
+use alloy_primitives::{Address, B256};
+use ocl::enums::{DeviceInfo, KernelWorkGroupInfo, KernelWorkGroupInfoResult};
+use ocl::{Buffer, Context, Device, Event, Kernel, Platform, Program, Queue};
+use std::collections::VecDeque;
 use std::error::Error;
-use std::io::prelude::*;
-use std::io::stdout;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
-
-use crate::{
-    output_file, PatternMode, CONTROL_CHARACTER, ERC6551_CONSTRUCTOR_HEADER, ERC6551_FOOTER,
-};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::midstate::{Midstate, RATE_LANES, address_lanes};
+use crate::multi::MultiBackend;
+use crate::progress::RunSummary;
+use crate::search::{Batch, BatchOutcome, Candidate, SearchBackend, search};
+use crate::tune::{self, LaunchShape};
+use crate::{Config, Pattern, PatternMode, Target, bytecode_footer, bytecode_header, first_match};
 
 // Keccak-256 OpenCL kernel
 const KECCAK_KERNEL: &str = r#"
@@ -76,87 +79,40 @@ void keccak_f1600(__private ulong *st) {
     }
 }
 
-void keccak256(__private uchar *input, int len, __private uchar *output) {
-    ulong st[25];
-    for (int i = 0; i < 25; i++) st[i] = 0;
-    
-    int rate = 136; // rate for keccak-256
-    int offset = 0;
-    
-    // Absorb
-    while (len >= rate) {
-        for (int i = 0; i < rate / 8; i++) {
-            ulong val = 0;
-            for (int j = 0; j < 8; j++) {
-                val |= ((ulong)input[offset + i * 8 + j]) << (j * 8);
-            }
-            st[i] ^= val;
-        }
-        keccak_f1600(st);
-        offset += rate;
-        len -= rate;
-    }
-    
-    // Final block with padding
-    uchar temp[136];
-    for (int i = 0; i < 136; i++) temp[i] = 0;
-    for (int i = 0; i < len; i++) temp[i] = input[offset + i];
-    temp[len] = 0x01;
-    temp[rate - 1] |= 0x80;
-    
-    for (int i = 0; i < rate / 8; i++) {
-        ulong val = 0;
-        for (int j = 0; j < 8; j++) {
-            val |= ((ulong)temp[i * 8 + j]) << (j * 8);
-        }
-        st[i] ^= val;
-    }
-    keccak_f1600(st);
-    
-    // Squeeze
-    for (int i = 0; i < 4; i++) {
-        ulong val = st[i];
-        for (int j = 0; j < 8; j++) {
-            output[i * 8 + j] = (uchar)(val >> (j * 8));
-        }
-    }
+// Byte `i` (0 to 19) of an address held in lanes 1 to 3 of a Keccak-256 state, where the
+// last 20 bytes of the hash sit; `lanes` points at lane 1. Pattern values and masks are packed
+// the same way.
+#define ADDRESS_BYTE(lanes, i) ((uchar)((lanes)[((i) + 4) >> 3] >> ((((i) + 4) & 7) << 3)))
+
+// XOR the 6-byte little-endian counter into the state at byte `position`, where it may
+// straddle two lanes
+inline void xor_counter(__private ulong *st, int position, ulong counter) {
+    int lane = position >> 3;
+    int shift = (position & 7) << 3;
+    st[lane] ^= counter << shift;
+    if (shift > 16) st[lane + 1] ^= counter >> (64 - shift);
 }
 
-// Check if hex string starts with pattern
-int check_prefix(__private uchar *addr, __global uchar *pattern, int pattern_len) {
-    for (int i = 0; i < pattern_len; i++) {
-        int byte_idx = i / 2;
-        int nibble;
-        if (i % 2 == 0) {
-            nibble = (addr[byte_idx] >> 4) & 0x0F;
-        } else {
-            nibble = addr[byte_idx] & 0x0F;
-        }
-        uchar expected = pattern[i];
-        uchar hex_char;
-        if (nibble < 10) {
-            hex_char = '0' + nibble;
-        } else {
-            hex_char = 'a' + nibble - 10;
-        }
-        if (hex_char != expected) return 0;
-    }
-    return 1;
+// Check address lanes against a nibble mask: (addr & mask) == value
+int check_mask(__private ulong *addr, __global ulong *mask_value, __global ulong *mask) {
+    return (((addr[0] & mask[0]) ^ mask_value[0])
+          | ((addr[1] & mask[1]) ^ mask_value[1])
+          | ((addr[2] & mask[2]) ^ mask_value[2])) == 0;
 }
 
 // Check if hex string contains pattern
-int check_contains(__private uchar *addr, __global uchar *pattern, int pattern_len) {
+int check_contains(__private ulong *addr, __global uchar *pattern, int pattern_len) {
     // For each possible starting position in the 40-char hex string
     for (int start = 0; start <= 40 - pattern_len; start++) {
         int match = 1;
         for (int i = 0; i < pattern_len && match; i++) {
             int pos = start + i;
-            int byte_idx = pos / 2;
+            uchar byte = ADDRESS_BYTE(addr, pos / 2);
             int nibble;
             if (pos % 2 == 0) {
-                nibble = (addr[byte_idx] >> 4) & 0x0F;
+                nibble = (byte >> 4) & 0x0F;
             } else {
-                nibble = addr[byte_idx] & 0x0F;
+                nibble = byte & 0x0F;
             }
             uchar hex_char;
             if (nibble < 10) {
@@ -171,336 +127,988 @@ int check_contains(__private uchar *addr, __global uchar *pattern, int pattern_l
     return 0;
 }
 
+// Count zero bytes at the start of the address
+int count_leading_zero_bytes(__private ulong *addr) {
+    int count = 0;
+    while (count < 20 && ADDRESS_BYTE(addr, count) == 0) count++;
+    return count;
+}
+
+// Count zero bytes anywhere in the address
+int count_zero_bytes(__private ulong *addr) {
+    int count = 0;
+    for (int i = 0; i < 20; i++) {
+        if (ADDRESS_BYTE(addr, i) == 0) count++;
+    }
+    return count;
+}
+
+// Count leading nibbles that match the mask, stopping at the first mismatch or wildcard
+int count_prefix_nibbles(__private ulong *addr, __global ulong *mask_value, __global ulong *mask) {
+    int count = 0;
+    for (int pos = 0; pos < 40; pos++) {
+        uchar nibble_mask = (pos % 2 == 0) ? 0xF0 : 0x0F;
+        uchar m = ADDRESS_BYTE(mask, pos / 2) & nibble_mask;
+        if (m == 0 || (ADDRESS_BYTE(addr, pos / 2) & m) != (ADDRESS_BYTE(mask_value, pos / 2) & m)) break;
+        count++;
+    }
+    return count;
+}
+
 __kernel void erc6551_crunch(
-    __global uchar *header_base,        // 47 bytes: control + registry + impl + random(6)
-    __global uchar *bytecode_header,    // 55 bytes
-    __global uchar *impl_addr,          // 20 bytes
-    __global uchar *bytecode_body,      // 6 bytes (random part)
-    __global uchar *bytecode_footer,    // 96 bytes
-    __global uchar *pattern,            // Pattern to match (hex chars)
-    int pattern_len,                    // Length of pattern
-    int pattern_mode,                   // 0=prefix, 1=contains
+    __global ulong *create2_block,      // 17 lanes: control, registry and salt, padded, with the
+                                        // counter and init code hash left zero
+    __global ulong *init_code_blocks,   // 34 lanes per target of the batch: both padded blocks
+                                        // of header, salt and footer, with the counter left zero
+    int target_start,                   // First target checked in this batch
+    int target_count,                   // Number of targets checked in this batch
+    __global int *pattern_params,       // 3 ints per pattern: mode, min score, contains length
+                                        // mode: 0=mask, 1=contains, 2=leading zero bytes,
+                                        // 3=zero bytes, 4=prefix nibbles matched against the mask
+    __global ulong *pattern_masks,      // 6 lanes per pattern: expected nibbles (3) followed by
+                                        // 0x0F/0xF0 for constrained nibbles (3), packed like
+                                        // address lanes
+    __global uchar *pattern_text,       // 40 bytes per pattern: contains pattern (hex chars)
+    int pattern_count,                  // Number of patterns in the table
+    int counter_offset,                 // Position of the 6-byte counter within the salt
     ulong salt_offset,                  // Starting salt offset
+    ulong salt_count,                   // Salts in this launch; the work size may be rounded up
+                                        // to a whole number of work groups
     __global ulong *results_salt,       // Output: found salts
+    __global int *results_target,       // Output: target of each found salt
     __global uchar *results_addr,       // Output: found addresses (20 bytes each)
     __global uchar *results_hash,       // Output: init code hashes (32 bytes each)
     __global int *results_count,        // Output: number of results found
-    int max_results                     // Maximum results to store
+    int max_results                     // Maximum results to store; matches past it are
+                                        // counted but not stored
 ) {
     ulong gid = get_global_id(0);
+    if (gid >= salt_count) return;
     ulong salt = salt_offset + gid;
-    
-    // Prepare salt bytes (6 bytes, little endian)
-    uchar salt_bytes[8];
-    for (int i = 0; i < 8; i++) {
-        salt_bytes[i] = (salt >> (i * 8)) & 0xFF;
-    }
-    
-    // Build bytecode for init_code_hash
-    // bytecode = header(55) + impl(20) + body(6) + salt(6) + footer(96) = 183 bytes
-    uchar bytecode[183];
-    for (int i = 0; i < 55; i++) bytecode[i] = bytecode_header[i];
-    for (int i = 0; i < 20; i++) bytecode[55 + i] = impl_addr[i];
-    for (int i = 0; i < 6; i++) bytecode[75 + i] = bytecode_body[i];
-    for (int i = 0; i < 6; i++) bytecode[81 + i] = salt_bytes[i];
-    for (int i = 0; i < 96; i++) bytecode[87 + i] = bytecode_footer[i];
-    
-    // Compute init_code_hash = keccak256(bytecode)
-    uchar init_code_hash[32];
-    keccak256(bytecode, 183, init_code_hash);
-    
-    // Build CREATE2 input
-    // create2_input = header(47) + salt(6) + init_code_hash(32) = 85 bytes
-    uchar create2_input[85];
-    for (int i = 0; i < 47; i++) create2_input[i] = header_base[i];
-    for (int i = 0; i < 6; i++) create2_input[47 + i] = salt_bytes[i];
-    for (int i = 0; i < 32; i++) create2_input[53 + i] = init_code_hash[i];
-    
-    // Compute address = keccak256(create2_input)[12:32]
-    uchar create2_hash[32];
-    keccak256(create2_input, 85, create2_hash);
-    
-    // Extract address (last 20 bytes)
-    uchar address[20];
-    for (int i = 0; i < 20; i++) {
-        address[i] = create2_hash[12 + i];
-    }
-    
-    // Check pattern based on mode
-    int matched = 0;
-    if (pattern_mode == 0) {
-        matched = check_prefix(address, pattern, pattern_len);
-    } else if (pattern_mode == 1) {
-        matched = check_contains(address, pattern, pattern_len);
-    }
-    
-    if (matched) {
-        int idx = atomic_add(results_count, 1);
-        if (idx < max_results) {
-            results_salt[idx] = salt;
-            for (int i = 0; i < 20; i++) {
-                results_addr[idx * 20 + i] = address[i];
+    ulong st[25];
+
+    for (int t = 0; t < target_count; t++) {
+        // init_code_hash = keccak256(header ++ salt ++ footer), two blocks with the counter in
+        // the first
+        __global ulong *blocks = init_code_blocks + t * 34;
+        for (int i = 0; i < 17; i++) st[i] = blocks[i];
+        for (int i = 17; i < 25; i++) st[i] = 0;
+        xor_counter(st, 55 + counter_offset, salt);
+        keccak_f1600(st);
+        for (int i = 0; i < 17; i++) st[i] ^= blocks[17 + i];
+        keccak_f1600(st);
+        ulong init_code_hash[4];
+        for (int i = 0; i < 4; i++) init_code_hash[i] = st[i];
+
+        // address = keccak256(0xff ++ registry ++ salt ++ init_code_hash)[12:32], one block
+        // with the hash starting five bytes into lane 6
+        for (int i = 0; i < 17; i++) st[i] = create2_block[i];
+        for (int i = 17; i < 25; i++) st[i] = 0;
+        xor_counter(st, 21 + counter_offset, salt);
+        for (int i = 0; i < 4; i++) {
+            st[6 + i] ^= init_code_hash[i] << 40;
+            st[7 + i] ^= init_code_hash[i] >> 24;
+        }
+        keccak_f1600(st);
+        __private ulong *address = st + 1;
+
+        // Check every pattern in the table, stopping at the first match
+        int matched = 0;
+        for (int p = 0; p < pattern_count && !matched; p++) {
+            int pattern_mode = pattern_params[p * 3];
+            int min_score = pattern_params[p * 3 + 1];
+            int pattern_len = pattern_params[p * 3 + 2];
+            __global ulong *mask_value = pattern_masks + p * 6;
+            __global ulong *mask = mask_value + 3;
+            if (pattern_mode == 0) {
+                matched = check_mask(address, mask_value, mask);
+            } else if (pattern_mode == 1) {
+                matched = check_contains(address, pattern_text + p * 40, pattern_len);
+            } else if (pattern_mode == 2) {
+                matched = count_leading_zero_bytes(address) >= min_score;
+            } else if (pattern_mode == 3) {
+                matched = count_zero_bytes(address) >= min_score;
+            } else if (pattern_mode == 4) {
+                matched = count_prefix_nibbles(address, mask_value, mask) >= min_score;
             }
-            for (int i = 0; i < 32; i++) {
-                results_hash[idx * 32 + i] = init_code_hash[i];
+        }
+        
+        if (matched) {
+            int idx = atomic_add(results_count, 1);
+            if (idx < max_results) {
+                results_salt[idx] = salt;
+                results_target[idx] = target_start + t;
+                for (int i = 0; i < 20; i++) {
+                    results_addr[idx * 20 + i] = ADDRESS_BYTE(address, i);
+                }
+                for (int i = 0; i < 32; i++) {
+                    results_hash[idx * 32 + i] = (uchar)(init_code_hash[i / 8] >> ((i % 8) * 8));
+                }
             }
         }
     }
 }
 "#;
 
-const GPU_BATCH_SIZE: usize = 1 << 22; // 4M addresses per batch
-const MAX_RESULTS_PER_BATCH: usize = 1024;
+const LAUNCHES_PER_BATCH: usize = 4;
+const DEFAULT_LAUNCH_SIZE: usize = 1 << 20; // 1M addresses per kernel launch
+const RESULTS_PER_LAUNCH: usize = 1024; // result buffer capacity until a launch overflows it
+const MAX_RESULTS_PER_LAUNCH: usize = 1 << 16; // result buffers grow no further
+
+/// Launch sizes tried by autotuning, smallest first.
+const TUNE_LAUNCH_SIZES: [usize; 4] = [1 << 18, 1 << 20, 1 << 22, 1 << 24];
+/// Work-group sizes tried by autotuning, `0` for the driver's choice.
+const TUNE_LOCAL_SIZES: [usize; 4] = [0, 64, 128, 256];
+/// How long each launch shape is timed for.
+const TUNE_TIME: Duration = Duration::from_millis(100);
+/// More leading zero bytes than an address has, so patterns timed with it never match.
+const UNREACHABLE_SCORE: u32 = 21;
 
-pub fn gpu(config: crate::Config) -> Result<(), Box<dyn Error>> {
-    // Initialize OpenCL
+/// Searches on the OpenCL devices selected by `--platform` and `--device`, see
+/// [`OpenClBackend`] and [`MultiBackend`].
+pub fn gpu(config: crate::Config) -> Result<RunSummary, Box<dyn Error>> {
+    let mut backends = opencl_backends(&config)?;
+    if backends.len() == 1 {
+        return search(&config, &mut backends.remove(0));
+    }
+    // Every device gets its own share of each batch
+    let backends = backends
+        .into_iter()
+        .map(|backend| Box::new(backend) as Box<dyn SearchBackend + Send>)
+        .collect();
+    search(&config, &mut MultiBackend::new(backends))
+}
+
+/// A backend for each device selected by `--platform` and `--device`.
+pub(crate) fn opencl_backends(config: &Config) -> Result<Vec<OpenClBackend>, Box<dyn Error>> {
     println!("🔍 Detecting GPU devices...");
+    Ok(select_devices(config.platform.as_ref(), &config.devices)?
+        .into_iter()
+        .map(|(platform, device)| OpenClBackend::new(platform, device))
+        .collect())
+}
 
-    let platform = Platform::default();
-    let device = Device::first(platform)?;
+/// OpenCL platforms or devices, by index in `--list-gpus`, part of a name, or all of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceSelector {
+    All,
+    Index(usize),
+    Name(String), // Lowercase, matched against any part of the name
+}
 
-    println!("🎮 GPU: {} ({})", device.name()?, device.vendor()?);
-    println!(
-        "   Max compute units: {}",
-        device.info(DeviceInfo::MaxComputeUnits)?
-    );
-    println!("   Max work group size: {}", device.max_wg_size()?);
-
-    let context = Context::builder()
-        .platform(platform)
-        .devices(device)
-        .build()?;
-    let queue = Queue::new(&context, device, None)?;
-
-    let program = Program::builder()
-        .src(KECCAK_KERNEL)
-        .devices(device)
-        .build(&context)?;
-
-    // Prepare data
-    let file = Arc::new(Mutex::new(output_file()));
-
-    // Prepare bytecode components
-    let mut bytecode_header = [0u8; 55];
-    bytecode_header[0..20].copy_from_slice(&ERC6551_CONSTRUCTOR_HEADER);
-    bytecode_header[20..40].copy_from_slice(&config.implement_address);
-    bytecode_header[40..].copy_from_slice(&ERC6551_FOOTER);
-
-    let mut bytecode_footer = [0u8; 96];
-    bytecode_footer[0..32].copy_from_slice(&config.chain_id);
-    bytecode_footer[44..64].copy_from_slice(&config.nft_address);
-    bytecode_footer[64..].copy_from_slice(&config.token_id);
-
-    // Parse pattern
-    let pattern = config.pattern.to_lowercase();
-    let pattern = pattern.strip_prefix("0x").unwrap_or(&pattern);
-    let pattern_bytes: Vec<u8> = pattern.as_bytes().to_vec();
-    let pattern_len = pattern_bytes.len();
-
-    let pattern_mode_int: i32 = match config.pattern_mode {
-        PatternMode::Prefix => 0,
-        PatternMode::Contains => 1,
+impl DeviceSelector {
+    /// The `items` this selects: all of them, the one at an index, or the first whose name
+    /// matches case-insensitively.
+    fn select<T: Copy>(
+        &self,
+        items: &[T],
+        name: impl Fn(&T) -> ocl::Result<String>,
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        match self {
+            DeviceSelector::All => Ok(items.to_vec()),
+            DeviceSelector::Index(index) => Ok(items.get(*index).copied().into_iter().collect()),
+            DeviceSelector::Name(part) => {
+                for item in items {
+                    if name(item)?.to_lowercase().contains(part.as_str()) {
+                        return Ok(vec![*item]);
+                    }
+                }
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+impl FromStr for DeviceSelector {
+    type Err = &'static str;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        let selector = selector.trim();
+        if selector.is_empty() {
+            return Err("--platform and --device take all, an index from --list-gpus or a name");
+        }
+        if selector.eq_ignore_ascii_case("all") {
+            return Ok(DeviceSelector::All);
+        }
+        Ok(match selector.parse() {
+            Ok(index) => DeviceSelector::Index(index),
+            Err(_) => DeviceSelector::Name(selector.to_lowercase()),
+        })
+    }
+}
+
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSelector::All => write!(f, "at all"),
+            DeviceSelector::Index(index) => write!(f, "number {}", index),
+            DeviceSelector::Name(name) => write!(f, "named like \"{}\"", name),
+        }
+    }
+}
+
+/// The devices picked by `--platform` and `--device`, in the order they were selected.
+///
+/// Every device selector applies to each selected platform, except that without `--platform`
+/// indices and `all` count within the default platform while names are looked up on every
+/// platform. Without any device selector each platform's first device is used.
+pub fn select_devices(
+    platform: Option<&DeviceSelector>,
+    devices: &[DeviceSelector],
+) -> Result<Vec<(Platform, Device)>, Box<dyn Error>> {
+    let platforms = match platform {
+        Some(selector) => {
+            let platforms = selector.select(&list_platforms()?, Platform::name)?;
+            if platforms.is_empty() {
+                return Err(format!(
+                    "there is no OpenCL platform {}; run --list-gpus to see the choices",
+                    selector
+                )
+                .into());
+            }
+            platforms
+        }
+        None => vec![Platform::from(ocl::core::default_platform()?)],
     };
+    if devices.is_empty() {
+        return platforms
+            .iter()
+            .map(|&platform| Ok((platform, Device::first(platform)?)))
+            .collect();
+    }
 
-    match config.pattern_mode {
-        PatternMode::Prefix => println!("🔍 Searching for addresses starting with: 0x{}", pattern),
-        PatternMode::Contains => println!("🔍 Searching for addresses containing: 0x{}", pattern),
+    let mut selected: Vec<(Platform, Device)> = Vec::new();
+    for selector in devices {
+        let searched = match (platform, selector) {
+            (None, DeviceSelector::Name(_)) => list_platforms()?,
+            _ => platforms.clone(),
+        };
+        let mut found = Vec::new();
+        for &platform in &searched {
+            let devices = Device::list_all(platform)?;
+            let matches = selector.select(&devices, Device::name)?;
+            found.extend(matches.into_iter().map(|device| (platform, device)));
+            // A name picks a single device, even if it matches on several platforms
+            if matches!(selector, DeviceSelector::Name(_)) && !found.is_empty() {
+                break;
+            }
+        }
+        if found.is_empty() {
+            let place = match searched.as_slice() {
+                [platform] => format!(" on {}", platform.name()?),
+                _ => String::new(),
+            };
+            return Err(format!(
+                "there is no OpenCL device {}{}; run --list-gpus to see the choices",
+                selector, place
+            )
+            .into());
+        }
+        for (platform, device) in found {
+            if selected.iter().any(|(_, other)| *other == device) {
+                return Err(format!("--device selects {} more than once", device.name()?).into());
+            }
+            selected.push((platform, device));
+        }
     }
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    Ok(selected)
+}
 
-    // Create buffers
-    let buf_bytecode_header = Buffer::<u8>::builder()
-        .queue(queue.clone())
-        .len(55)
-        .copy_host_slice(&bytecode_header)
-        .build()?;
-
-    let buf_impl_addr = Buffer::<u8>::builder()
-        .queue(queue.clone())
-        .len(20)
-        .copy_host_slice(&config.implement_address)
-        .build()?;
-
-    let buf_bytecode_footer = Buffer::<u8>::builder()
-        .queue(queue.clone())
-        .len(96)
-        .copy_host_slice(&bytecode_footer)
-        .build()?;
-
-    let buf_pattern = Buffer::<u8>::builder()
-        .queue(queue.clone())
-        .len(pattern_bytes.len().max(1))
-        .copy_host_slice(if pattern_bytes.is_empty() {
-            &[0u8]
-        } else {
-            &pattern_bytes
+/// Every OpenCL platform, or an error where `Platform::list` would panic.
+fn list_platforms() -> Result<Vec<Platform>, Box<dyn Error>> {
+    Ok(Platform::list_from_core(ocl::core::get_platform_ids()?))
+}
+
+/// Hashes salts with an OpenCL kernel on one device.
+///
+/// Each batch is split into kernel launches, with two in flight at once: while the device
+/// works on one launch, the results of the previous one are read back and decoded.
+///
+/// The launch size and work-group size come from `--launch-size` and `--local-size`, or from
+/// timing a few of each on the device when it is prepared; `--tune-cache` keeps the fastest
+/// for later runs.
+#[derive(Debug)]
+pub struct OpenClBackend {
+    platform: Platform,
+    device: Device,
+    launches_in_flight: usize,
+    session: Option<Session>, // set up by `prepare`
+}
+
+/// The buffers every batch writes to, and the launch slots.
+#[derive(Debug)]
+struct Session {
+    description: Vec<String>,
+    queue: Queue,
+    bytecode_header: [u8; 55],
+    bytecode_footers: Vec<[u8; 96]>,
+    pattern_params: Vec<i32>,
+    buf_create2_block: Buffer<u64>,
+    buf_init_code_blocks: Buffer<u64>, // grown to the widest batch's targets
+    buf_pattern_params: Buffer<i32>,
+    shape: LaunchShape,
+    slots: Vec<Slot>,
+}
+
+/// One kernel launch in flight. Every slot has its own queue, so reading its results doesn't
+/// wait for the launches queued behind it, and its own kernel and result buffers.
+#[derive(Debug)]
+struct Slot {
+    queue: Queue,
+    kernel: Kernel,
+    done: Event,          // set when the launch is enqueued
+    counters: Range<u64>, // of the last launch
+    local_size: usize,
+    results: ResultBuffers,
+    buf_results_count: Buffer<i32>,
+}
+
+/// Where a launch writes its matches, up to `capacity` of them. The kernel counts every match,
+/// so the count may be higher.
+#[derive(Debug)]
+struct ResultBuffers {
+    capacity: usize,
+    salt: Buffer<u64>,
+    target: Buffer<i32>,
+    addr: Buffer<u8>,
+    hash: Buffer<u8>,
+}
+
+impl ResultBuffers {
+    fn new(queue: &Queue, capacity: usize) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            capacity,
+            salt: Buffer::<u64>::builder()
+                .queue(queue.clone())
+                .len(capacity)
+                .build()?,
+            target: Buffer::<i32>::builder()
+                .queue(queue.clone())
+                .len(capacity)
+                .build()?,
+            addr: Buffer::<u8>::builder()
+                .queue(queue.clone())
+                .len(capacity * 20)
+                .build()?,
+            hash: Buffer::<u8>::builder()
+                .queue(queue.clone())
+                .len(capacity * 32)
+                .build()?,
         })
-        .build()?;
-
-    // Output buffers
-    let buf_results_salt = Buffer::<u64>::builder()
-        .queue(queue.clone())
-        .len(MAX_RESULTS_PER_BATCH)
-        .build()?;
-
-    let buf_results_addr = Buffer::<u8>::builder()
-        .queue(queue.clone())
-        .len(MAX_RESULTS_PER_BATCH * 20)
-        .build()?;
-
-    let buf_results_hash = Buffer::<u8>::builder()
-        .queue(queue.clone())
-        .len(MAX_RESULTS_PER_BATCH * 32)
-        .build()?;
-
-    let buf_results_count = Buffer::<i32>::builder()
-        .queue(queue.clone())
-        .len(1)
-        .build()?;
-
-    // Speed tracking
-    let total_checked = Arc::new(AtomicU64::new(0));
-    let found_count = Arc::new(AtomicU64::new(0));
-    let start_time = Instant::now();
-
-    // Spawn a thread to print speed stats
-    let total_checked_clone = Arc::clone(&total_checked);
-    let found_count_clone = Arc::clone(&found_count);
-    std::thread::spawn(move || {
-        let mut last_count = 0u64;
-        loop {
-            std::thread::sleep(std::time::Duration::from_secs(2));
-            let current = total_checked_clone.load(Ordering::Relaxed);
-            let found = found_count_clone.load(Ordering::Relaxed);
-            let elapsed = start_time.elapsed().as_secs_f64();
-            let speed = current as f64 / elapsed;
-            let instant_speed = (current - last_count) as f64 / 2.0;
-            last_count = current;
-
-            eprint!(
-                "\r🚀 GPU Speed: {:.2}M/s | Avg: {:.2}M/s | Checked: {}M | Found: {} | Time: {:.0}s    ",
-                instant_speed / 1_000_000.0,
-                speed / 1_000_000.0,
-                current / 1_000_000,
-                found,
-                elapsed
-            );
-            let _ = std::io::stderr().flush();
+    }
+}
+
+impl OpenClBackend {
+    /// A backend for `device`, e.g. one picked by [`select_devices`].
+    pub fn new(platform: Platform, device: Device) -> Self {
+        Self {
+            platform,
+            device,
+            launches_in_flight: 2,
+            session: None,
+        }
+    }
+
+    /// Keeps `launches` kernel launches in flight instead of two; `1` waits for each launch
+    /// before queueing the next.
+    pub fn with_launches_in_flight(mut self, launches: usize) -> Self {
+        self.launches_in_flight = launches.max(1);
+        self
+    }
+
+    /// The launch shape to use, and where it came from: `--launch-size` and `--local-size`, the
+    /// tuning cache, or timing the sizes neither of them fixes.
+    fn choose_shape(
+        &mut self,
+        config: &Config,
+    ) -> Result<(LaunchShape, &'static str), Box<dyn Error>> {
+        let session = self
+            .session
+            .as_ref()
+            .ok_or("the OpenCL backend was not prepared")?;
+        // The kernel may allow smaller work groups than the device does
+        let max_local_size = match session.slots[0]
+            .kernel
+            .wg_info(self.device, KernelWorkGroupInfo::WorkGroupSize)?
+        {
+            KernelWorkGroupInfoResult::WorkGroupSize(size) => size,
+            _ => self.device.max_wg_size()?,
+        };
+        let device_name = self.device.name()?;
+        if let Some(local_size) = config.local_size
+            && local_size > max_local_size
+        {
+            return Err(format!(
+                "--local-size {} is larger than the {} that {} allows",
+                local_size, max_local_size, device_name
+            )
+            .into());
+        }
+
+        let launch_sizes = match config.launch_size {
+            Some(launch_size) => vec![launch_size],
+            None => TUNE_LAUNCH_SIZES.to_vec(),
+        };
+        let local_sizes: Vec<usize> = match config.local_size {
+            Some(local_size) => vec![local_size],
+            None => TUNE_LOCAL_SIZES
+                .into_iter()
+                .filter(|local_size| *local_size <= max_local_size)
+                .collect(),
+        };
+        if let ([launch_size], [local_size]) = (launch_sizes.as_slice(), local_sizes.as_slice()) {
+            let shape = LaunchShape {
+                launch_size: *launch_size,
+                local_size: *local_size,
+            };
+            return Ok((shape, "set"));
+        }
+
+        if let Some(path) = &config.tune_cache
+            && !config.retune
+            && let Some(shape) = tune::cached_shape(path, &device_name)?
+            && config
+                .launch_size
+                .is_none_or(|launch_size| launch_size == shape.launch_size)
+            && config
+                .local_size
+                .is_none_or(|local_size| local_size == shape.local_size)
+            && shape.local_size <= max_local_size
+        {
+            return Ok((shape, "cached"));
+        }
+
+        let shape = self.tune(config, &launch_sizes, &local_sizes)?;
+        // Only a shape picked from every size is worth remembering
+        if let Some(path) = &config.tune_cache
+            && config.launch_size.is_none()
+            && config.local_size.is_none()
+        {
+            tune::save_shape(path, &device_name, shape)?;
+        }
+        Ok((shape, "tuned"))
+    }
+
+    /// Times launch sizes with the first work-group size, growing them while that is clearly
+    /// faster, then each work-group size at the fastest launch size.
+    fn tune(
+        &mut self,
+        config: &Config,
+        launch_sizes: &[usize],
+        local_sizes: &[usize],
+    ) -> Result<LaunchShape, Box<dyn Error>> {
+        // Patterns are swapped for ones that never match while timing, so launches of easy
+        // patterns don't fill and overflow the result buffers and time their recovery instead.
+        // Mode 2 counts leading zero bytes
+        let never_matching = [2, UNREACHABLE_SCORE as i32, 0].repeat(config.patterns.len());
+        let pattern_params = self.swap_pattern_params(never_matching)?;
+
+        let mut best = LaunchShape {
+            launch_size: launch_sizes[0],
+            local_size: local_sizes[0],
+        };
+        let mut best_speed = self.time_shape(config, best)?;
+        for &launch_size in &launch_sizes[1..] {
+            let shape = LaunchShape {
+                launch_size,
+                ..best
+            };
+            let speed = self.time_shape(config, shape)?;
+            // Larger launches take longer to stop, so they have to be worth it
+            if speed < best_speed * 1.05 {
+                break;
+            }
+            (best, best_speed) = (shape, speed);
+        }
+        for &local_size in &local_sizes[1..] {
+            let shape = LaunchShape { local_size, ..best };
+            let speed = self.time_shape(config, shape)?;
+            if speed > best_speed {
+                (best, best_speed) = (shape, speed);
+            }
+        }
+        self.swap_pattern_params(pattern_params)?;
+        Ok(best)
+    }
+
+    /// Salts per second hashed for the first target with `shape`, timed after one launch to
+    /// warm up.
+    fn time_shape(&mut self, config: &Config, shape: LaunchShape) -> Result<f64, Box<dyn Error>> {
+        self.session
+            .as_mut()
+            .ok_or("the OpenCL backend was not prepared")?
+            .shape = shape;
+        let launch_size = shape.launch_size as u64;
+        let mut batch = Batch {
+            salt_base: config.salt_template.with_segment(&[0; 6]),
+            counters: 0..launch_size,
+            targets: 0..1,
+            // Optimize mode writes this into the pattern table, which must still never match
+            min_score: UNREACHABLE_SCORE,
+            first_only: false,
+        };
+        let start = Instant::now();
+        self.run_batch(config, &batch, &|| false)?;
+
+        // Enough launches to take about `TUNE_TIME`, judging by the first
+        let launches = (TUNE_TIME.as_secs_f64() / start.elapsed().as_secs_f64().max(1e-6))
+            .ceil()
+            .clamp(2.0, 16.0) as u64;
+        batch.counters = launch_size..launch_size * (launches + 1);
+        let start = Instant::now();
+        let checked = self.run_batch(config, &batch, &|| false)?.checked;
+        Ok(checked as f64 / start.elapsed().as_secs_f64())
+    }
+
+    /// Swaps the kernel's pattern table for `pattern_params`, returning the old one.
+    fn swap_pattern_params(
+        &mut self,
+        pattern_params: Vec<i32>,
+    ) -> Result<Vec<i32>, Box<dyn Error>> {
+        let session = self
+            .session
+            .as_mut()
+            .ok_or("the OpenCL backend was not prepared")?;
+        let old = std::mem::replace(&mut session.pattern_params, pattern_params);
+        session
+            .buf_pattern_params
+            .write(&session.pattern_params)
+            .enq()?;
+        Ok(old)
+    }
+}
+
+impl Slot {
+    /// Queues the kernel for `counters` in work groups of `local_size`, after clearing the
+    /// result count.
+    fn launch(&mut self, counters: &Range<u64>, local_size: usize) -> Result<(), Box<dyn Error>> {
+        self.counters = counters.clone();
+        self.local_size = local_size;
+        self.buf_results_count.cmd().fill(0, None).enq()?;
+        self.kernel.set_arg("salt_offset", counters.start)?;
+        self.kernel
+            .set_arg("salt_count", counters.end - counters.start)?;
+        self.done = Event::empty();
+        let count = (counters.end - counters.start) as usize;
+        let mut cmd = self.kernel.cmd().enew(&mut self.done);
+        cmd = match local_size {
+            0 => cmd.global_work_size(count),
+            local_size => cmd
+                .global_work_size(count.next_multiple_of(local_size))
+                .local_work_size(local_size),
+        };
+        unsafe {
+            cmd.enq()?;
+        }
+        self.queue.flush()?;
+        Ok(())
+    }
+
+    /// Waits for the launch to finish and reads back what it found. Returns how many matches
+    /// didn't fit the result buffers; those are found again by growing the buffers and
+    /// hashing the launch's salts once more in pieces small enough, so `candidates` gets every
+    /// match all the same.
+    fn collect(&mut self, candidates: &mut Vec<Candidate>) -> Result<u64, Box<dyn Error>> {
+        self.done.wait_for()?;
+        let count = self.result_count()?;
+        if count <= self.results.capacity {
+            return self.read_results(count, candidates).map(|_| 0);
+        }
+
+        let overflowed = count - self.results.capacity;
+        if self.results.capacity < MAX_RESULTS_PER_LAUNCH {
+            self.results = ResultBuffers::new(
+                &self.queue,
+                count.next_power_of_two().min(MAX_RESULTS_PER_LAUNCH),
+            )?;
+            self.kernel.set_arg("results_salt", &self.results.salt)?;
+            self.kernel
+                .set_arg("results_target", &self.results.target)?;
+            self.kernel.set_arg("results_addr", &self.results.addr)?;
+            self.kernel.set_arg("results_hash", &self.results.hash)?;
+            self.kernel
+                .set_arg("max_results", self.results.capacity as i32)?;
+        }
+
+        // Pieces are sized for half the buffers, going by the matches of the range they split;
+        // a piece that still overflows is split again
+        let local_size = self.local_size;
+        let mut pending = vec![(self.counters.clone(), count)];
+        while let Some((counters, count)) = pending.pop() {
+            let len = counters.end - counters.start;
+            if len == 1 {
+                return Err(format!(
+                    "salt {} matches {} times, more than the GPU result buffers hold",
+                    counters.start, count
+                )
+                .into());
+            }
+            let pieces = ((count * 2).div_ceil(self.results.capacity) as u64).min(len);
+            for i in 0..pieces {
+                let piece =
+                    counters.start + len * i / pieces..counters.start + len * (i + 1) / pieces;
+                self.launch(&piece, local_size)?;
+                self.done.wait_for()?;
+                let count = self.result_count()?;
+                if count <= self.results.capacity {
+                    self.read_results(count, candidates)?;
+                } else {
+                    pending.push((piece, count));
+                }
+            }
+        }
+        Ok(overflowed as u64)
+    }
+
+    /// Matches the last launch found, including any past the result buffers' capacity.
+    fn result_count(&self) -> Result<usize, Box<dyn Error>> {
+        let mut result_count = [0i32].to_vec();
+        self.buf_results_count.read(&mut result_count).enq()?;
+        Ok(result_count[0].max(0) as usize)
+    }
+
+    /// Reads the first `count` results of the last launch.
+    fn read_results(
+        &self,
+        count: usize,
+        candidates: &mut Vec<Candidate>,
+    ) -> Result<(), Box<dyn Error>> {
+        if count == 0 {
+            return Ok(());
         }
-    });
 
-    // Main loop
-    let mut global_salt_offset: u64 = 0;
+        let mut results_salt = vec![0u64; count];
+        let mut results_target = vec![0i32; count];
+        let mut results_addr = vec![0u8; count * 20];
+        let mut results_hash = vec![0u8; count * 32];
+        self.results.salt.read(&mut results_salt).enq()?;
+        self.results.target.read(&mut results_target).enq()?;
+        self.results.addr.read(&mut results_addr).enq()?;
+        self.results.hash.read(&mut results_hash).enq()?;
 
-    loop {
-        // Generate random header for this batch
-        let mut header = [0u8; 47];
-        header[0] = CONTROL_CHARACTER;
-        header[1..21].copy_from_slice(&config.resistry_address);
-        header[21..41].copy_from_slice(&config.implement_address);
-        let random_bytes: [u8; 6] = FixedBytes::<6>::random().0;
-        header[41..].copy_from_slice(&random_bytes);
+        for i in 0..count {
+            candidates.push(Candidate {
+                counter: results_salt[i],
+                target: results_target[i] as usize,
+                address: Address::from_slice(&results_addr[i * 20..(i + 1) * 20]),
+                init_code_hash: B256::from_slice(&results_hash[i * 32..(i + 1) * 32]),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl SearchBackend for OpenClBackend {
+    fn name(&self) -> &'static str {
+        "gpu"
+    }
 
-        let bytecode_body = random_bytes;
+    fn speed_label(&self) -> &'static str {
+        "🚀 GPU Speed"
+    }
+
+    fn batch_size(&self) -> u64 {
+        let launch_size = self
+            .session
+            .as_ref()
+            .map_or(DEFAULT_LAUNCH_SIZE, |session| session.shape.launch_size);
+        (launch_size * LAUNCHES_PER_BATCH) as u64
+    }
+
+    fn prepare(&mut self, config: &Config, targets: &[Target]) -> Result<(), Box<dyn Error>> {
+        // Initialize OpenCL
+        let (platform, device) = (self.platform, self.device);
+
+        let description = vec![
+            format!("🎮 GPU: {} ({})", device.name()?, device.vendor()?),
+            format!(
+                "   Max compute units: {}",
+                device.info(DeviceInfo::MaxComputeUnits)?
+            ),
+            format!("   Max work group size: {}", device.max_wg_size()?),
+        ];
 
-        // Create per-batch buffers
-        let buf_header = Buffer::<u8>::builder()
+        let context = Context::builder()
+            .platform(platform)
+            .devices(device)
+            .build()?;
+        let queue = Queue::new(&context, device, None)?;
+
+        let program = Program::builder()
+            .src(KECCAK_KERNEL)
+            .devices(device)
+            .build(&context)?;
+
+        let bytecode_header = bytecode_header(&config.implement_address);
+        let bytecode_footers: Vec<[u8; 96]> = targets.iter().map(bytecode_footer).collect();
+
+        // Pack the patterns into a table the kernel walks for every address; textual patterns
+        // other than `contains` are checked as a nibble mask on the address lanes
+        let pattern_params = pattern_params(&config.patterns);
+        let mut pattern_masks = Vec::with_capacity(config.patterns.len() * 6);
+        let mut pattern_text = vec![0u8; config.patterns.len() * 40];
+        for (i, pattern) in config.patterns.iter().enumerate() {
+            pattern_masks.extend_from_slice(&address_lanes(&pattern.address_mask().value));
+            pattern_masks.extend_from_slice(&address_lanes(&pattern.address_mask().mask));
+            if pattern.mode == PatternMode::Contains {
+                let text = pattern.pattern.to_lowercase();
+                pattern_text[i * 40..i * 40 + text.len()].copy_from_slice(text.as_bytes());
+            }
+        }
+
+        // Create buffers; the hash blocks are rewritten at the start of every batch
+        let buf_create2_block = Buffer::<u64>::builder()
             .queue(queue.clone())
-            .len(47)
-            .copy_host_slice(&header)
+            .len(RATE_LANES)
             .build()?;
 
-        let buf_bytecode_body = Buffer::<u8>::builder()
+        let buf_init_code_blocks = Buffer::<u64>::builder()
             .queue(queue.clone())
-            .len(6)
-            .copy_host_slice(&bytecode_body)
+            .len(2 * RATE_LANES)
             .build()?;
 
-        // Reset results count
-        let zero = [0i32].to_vec();
-        buf_results_count.write(&zero).enq()?;
+        let buf_pattern_params = Buffer::<i32>::builder()
+            .queue(queue.clone())
+            .len(pattern_params.len())
+            .copy_host_slice(&pattern_params)
+            .build()?;
 
-        // Build and execute kernel
-        let kernel = Kernel::builder()
-            .program(&program)
-            .name("erc6551_crunch")
+        let buf_pattern_masks = Buffer::<u64>::builder()
             .queue(queue.clone())
-            .global_work_size(GPU_BATCH_SIZE)
-            .arg(&buf_header)
-            .arg(&buf_bytecode_header)
-            .arg(&buf_impl_addr)
-            .arg(&buf_bytecode_body)
-            .arg(&buf_bytecode_footer)
-            .arg(&buf_pattern)
-            .arg(pattern_len as i32)
-            .arg(pattern_mode_int)
-            .arg(global_salt_offset)
-            .arg(&buf_results_salt)
-            .arg(&buf_results_addr)
-            .arg(&buf_results_hash)
-            .arg(&buf_results_count)
-            .arg(MAX_RESULTS_PER_BATCH as i32)
+            .len(pattern_masks.len())
+            .copy_host_slice(&pattern_masks)
             .build()?;
 
-        unsafe {
-            kernel.enq()?;
-        }
-        queue.finish()?;
+        let buf_pattern_text = Buffer::<u8>::builder()
+            .queue(queue.clone())
+            .len(pattern_text.len())
+            .copy_host_slice(&pattern_text)
+            .build()?;
 
-        // Update counters
-        total_checked.fetch_add(GPU_BATCH_SIZE as u64, Ordering::Relaxed);
-        global_salt_offset += GPU_BATCH_SIZE as u64;
+        // Each slot gets its own queue, output buffers and kernel, built once and reused
+        let mut slots = Vec::with_capacity(self.launches_in_flight);
+        for _ in 0..self.launches_in_flight {
+            let queue = Queue::new(&context, device, None)?;
 
-        // Check for results
-        let mut result_count = [0i32].to_vec();
-        buf_results_count.read(&mut result_count).enq()?;
+            // Output buffers, replaced by larger ones if a launch overflows them
+            let results = ResultBuffers::new(&queue, RESULTS_PER_LAUNCH)?;
 
-        if result_count[0] > 0 {
-            let count = (result_count[0] as usize).min(MAX_RESULTS_PER_BATCH);
+            let buf_results_count = Buffer::<i32>::builder()
+                .queue(queue.clone())
+                .len(1)
+                .build()?;
 
-            let mut results_salt = vec![0u64; count];
-            let mut results_addr = vec![0u8; count * 20];
-            let mut results_hash = vec![0u8; count * 32];
+            // The targets, their blocks, the salt offset and the result buffers change between
+            // launches and are set in place
+            let kernel = Kernel::builder()
+                .program(&program)
+                .name("erc6551_crunch")
+                .queue(queue.clone())
+                .arg(&buf_create2_block)
+                .arg_named("init_code_blocks", &buf_init_code_blocks)
+                .arg_named("target_start", 0i32)
+                .arg_named("target_count", 0i32)
+                .arg(&buf_pattern_params)
+                .arg(&buf_pattern_masks)
+                .arg(&buf_pattern_text)
+                .arg(config.patterns.len() as i32)
+                .arg(config.salt_template.counter_offset() as i32)
+                .arg_named("salt_offset", 0u64)
+                .arg_named("salt_count", 0u64)
+                .arg_named("results_salt", &results.salt)
+                .arg_named("results_target", &results.target)
+                .arg_named("results_addr", &results.addr)
+                .arg_named("results_hash", &results.hash)
+                .arg(&buf_results_count)
+                .arg_named("max_results", results.capacity as i32)
+                .build()?;
 
-            buf_results_salt.read(&mut results_salt).enq()?;
-            buf_results_addr.read(&mut results_addr).enq()?;
-            buf_results_hash.read(&mut results_hash).enq()?;
+            slots.push(Slot {
+                queue,
+                kernel,
+                done: Event::empty(),
+                counters: 0..0,
+                local_size: 0,
+                results,
+                buf_results_count,
+            });
+        }
 
-            for i in 0..count {
-                let salt = results_salt[i];
-                let addr = &results_addr[i * 20..(i + 1) * 20];
+        self.session = Some(Session {
+            description,
+            queue,
+            bytecode_header,
+            bytecode_footers,
+            pattern_params,
+            buf_create2_block,
+            buf_init_code_blocks,
+            buf_pattern_params,
+            shape: LaunchShape {
+                launch_size: DEFAULT_LAUNCH_SIZE,
+                local_size: 0,
+            },
+            slots,
+        });
 
-                found_count.fetch_add(1, Ordering::Relaxed);
+        let (shape, how) = self.choose_shape(config)?;
+        let session = self.session.as_mut().expect("just prepared");
+        session.shape = shape;
+        session
+            .description
+            .push(format!("   Launches: {} ({})", shape, how));
+        Ok(())
+    }
 
-                let header_hex = hex::encode(&header);
-                let salt_bytes = salt.to_le_bytes();
-                let salt_hex = hex::encode(&salt_bytes[..6]);
-                let full_salt = format!("0x{}{}", &header_hex[42..], salt_hex);
-                let addr_hex = hex::encode(addr);
+    fn describe(&self) -> Vec<String> {
+        self.session
+            .as_ref()
+            .map_or_else(Vec::new, |session| session.description.clone())
+    }
 
-                let output = format!("\n{} => 0x{}\n", full_salt, addr_hex);
-                print!("{output}");
-                let _ = stdout().flush();
+    fn run_batch(
+        &mut self,
+        config: &Config,
+        batch: &Batch,
+        stop: &(dyn Fn() -> bool + Sync),
+    ) -> Result<BatchOutcome, Box<dyn Error>> {
+        let session = self
+            .session
+            .as_mut()
+            .ok_or("the OpenCL backend was not prepared")?;
 
-                {
-                    let mut f = file.lock().unwrap();
-                    writeln!(f, "{} => 0x{}", full_salt, addr_hex)
-                        .expect("Couldn't write to result.txt file.");
-                }
+        // Everything but the counter is packed into the hashes' blocks once per batch. Nothing
+        // is in flight between batches, so the shared inputs can be written directly
+        let midstate = Midstate::new(
+            &config.resistry_address,
+            &session.bytecode_header,
+            &session.bytecode_footers[batch.targets.clone()],
+            &batch.salt_base,
+            config.salt_template.counter_offset(),
+        );
+        let init_code_blocks: Vec<u64> = midstate
+            .init_code
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .collect();
+        if init_code_blocks.len() > session.buf_init_code_blocks.len() {
+            session.buf_init_code_blocks = Buffer::<u64>::builder()
+                .queue(session.queue.clone())
+                .len(init_code_blocks.len())
+                .build()?;
+            for slot in &session.slots {
+                slot.kernel
+                    .set_arg("init_code_blocks", &session.buf_init_code_blocks)?;
+            }
+        }
+        session
+            .buf_create2_block
+            .write(&midstate.create2[..])
+            .enq()?;
+        session
+            .buf_init_code_blocks
+            .write(&init_code_blocks)
+            .enq()?;
+
+        // Optimize mode has a single pattern whose threshold rises with each improvement
+        if config.optimize {
+            session.pattern_params[1] = batch.min_score as i32;
+            session
+                .buf_pattern_params
+                .write(&session.pattern_params)
+                .enq()?;
+        }
+
+        for slot in &session.slots {
+            slot.kernel
+                .set_arg("target_start", batch.targets.start as i32)?;
+            slot.kernel
+                .set_arg("target_count", batch.targets.len() as i32)?;
+        }
+
+        // Slots are used in turn; before one is reused its previous launch is collected, which
+        // overlaps with the device working on the launches queued after it
+        let mut outcome = BatchOutcome::default();
+        let mut in_flight: VecDeque<(usize, Range<u64>)> = VecDeque::new();
+        let mut start = batch.counters.start;
+        let mut next_slot = 0;
+        loop {
+            let full = in_flight.len() == session.slots.len();
+            // Only a match that holds up on the host ends a first-only batch early, as the
+            // kernel ignores letter case
+            let done = start == batch.counters.end
+                || stop()
+                || batch.first_only
+                    && outcome
+                        .candidates
+                        .iter()
+                        .any(|candidate| first_match(config, &candidate.address).is_some());
+            if full || done {
+                let Some((slot, counters)) = in_flight.pop_front() else {
+                    break;
+                };
+                outcome.overflowed += session.slots[slot].collect(&mut outcome.candidates)?;
+                outcome.checked += counters.end - counters.start;
+                continue;
             }
+
+            let counters =
+                start..(start + session.shape.launch_size as u64).min(batch.counters.end);
+            session.slots[next_slot].launch(&counters, session.shape.local_size)?;
+            start = counters.end;
+            in_flight.push_back((next_slot, counters));
+            next_slot = (next_slot + 1) % session.slots.len();
         }
+
+        Ok(outcome)
     }
 }
 
-/// List available GPU devices
+/// Kernel parameters for each pattern: mode, minimum score and `contains` length.
+fn pattern_params(patterns: &[Pattern]) -> Vec<i32> {
+    patterns
+        .iter()
+        .flat_map(|pattern| {
+            let mode = match pattern.mode {
+                PatternMode::Contains => 1,
+                PatternMode::LeadingZeroBytes => 2,
+                PatternMode::TotalZeroBytes => 3,
+                PatternMode::PatternLength => 4,
+                _ => 0,
+            };
+            [mode, pattern.min_score as i32, pattern.pattern.len() as i32]
+        })
+        .collect()
+}
+
+/// List available GPU devices, with the indices `--platform` and `--device` take
 pub fn list_gpus() -> Result<(), Box<dyn Error>> {
     println!("Available OpenCL devices:");
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-    for platform in Platform::list() {
-        println!("Platform: {}", platform.name()?);
-        for device in Device::list_all(platform)? {
-            println!("  📊 {} ({})", device.name()?, device.vendor()?);
+    // `Platform::list` and `Platform::default` panic without an OpenCL platform
+    let platforms = list_platforms()?;
+    let default_platform = ocl::core::default_platform()?;
+    for (platform_index, platform) in platforms.into_iter().enumerate() {
+        let default = if platform.as_ptr() == default_platform.as_ptr() {
+            " (default)"
+        } else {
+            ""
+        };
+        println!(
+            "Platform {}: {}{}",
+            platform_index,
+            platform.name()?,
+            default
+        );
+        for (device_index, device) in Device::list_all(platform)?.into_iter().enumerate() {
+            println!(
+                "  [{}] 📊 {} ({})",
+                device_index,
+                device.name()?,
+                device.vendor()?
+            );
             println!("     Type: {}", device.info(DeviceInfo::Type)?);
             println!(
                 "     Compute Units: {}",
@@ -512,6 +1120,10 @@ pub fn list_gpus() -> Result<(), Box<dyn Error>> {
             }
         }
     }
+    println!();
+    println!(
+        "Select with --platform <index|name|all> and --device <index|name|all>, or a list of devices."
+    );
 
     Ok(())
 }
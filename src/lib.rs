@@ -1,23 +1,46 @@
-use alloy_primitives::{Address, FixedBytes, hex};
-use rayon::prelude::*;
+use alloy_primitives::{Address, U256, hex, ruint};
 use std::error::Error;
-use std::fs::{File, OpenOptions};
-use std::io::prelude::*;
-use std::io::stdout;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
-use tiny_keccak::{Hasher, Keccak};
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+pub mod checkpoint;
+pub mod cpu;
 pub mod gpu;
-pub use gpu::{gpu, list_gpus};
+mod midstate;
+pub mod multi;
+pub mod output;
+pub mod pattern;
+pub mod progress;
+pub mod salt;
+pub mod search;
+pub mod tba;
+mod tune;
+pub mod verify;
+pub use checkpoint::SearchPosition;
+pub use cpu::CpuBackend;
+pub use gpu::{DeviceSelector, OpenClBackend, gpu, list_gpus, select_devices};
+pub use multi::{MultiBackend, hybrid};
+pub use output::OutputFormat;
+pub use pattern::{AddressMask, Pattern, address_score, checksum_score};
+pub use progress::{BestMatch, CancelHandle, Meter, RunSummary, StopReason, interrupt};
+pub use salt::SaltTemplate;
+pub use search::{Batch, BatchOutcome, Candidate, Match, SearchBackend, Searcher, search};
+pub use tba::{TbaParams, compute_tba_address, tba_init_code, tba_init_code_hash};
+pub use verify::{VerifyConfig, verify};
+
 const CONTROL_CHARACTER: u8 = 0xff;
 const MAX_INCREMENTER: u64 = 0xffffffffffff;
+/// Most tokens in a collection. Every token's target is kept in memory (about 120 bytes), and
+/// each backend keeps its init code footer (96 bytes), so the limit costs about 120 MB plus
+/// 100 MB per backend.
+const MAX_COLLECTION_SIZE: usize = 1 << 20;
 
 /// ERC6551 Registry address (same on all EVM chains)
 /// https://eips.ethereum.org/EIPS/eip-6551
 pub const ERC6551_REGISTRY: [u8; 20] = [
-    0x00, 0x00, 0x00, 0x00, 0x65, 0x51, 0xc1, 0x94, 0x87, 0x81,
-    0x46, 0x12, 0xe5, 0x8F, 0xE0, 0x68, 0x13, 0x77, 0x57, 0x58,
+    0x00, 0x00, 0x00, 0x00, 0x65, 0x51, 0xc1, 0x94, 0x87, 0x81, 0x46, 0x12, 0xe5, 0x8F, 0xE0, 0x68,
+    0x13, 0x77, 0x57, 0x58,
 ];
 
 const ERC6551_CONSTRUCTOR_HEADER: [u8; 20] = [
@@ -30,42 +53,188 @@ const ERC6551_FOOTER: [u8; 15] = [
 pub struct Config {
     pub resistry_address: [u8; 20],
     pub implement_address: [u8; 20],
+    /// Accounts checked for every salt, each with its own init code.
+    pub targets: Vec<Target>,
+    /// Tokens searched one after another with the single target's chain and NFT, one result
+    /// each; empty otherwise.
+    pub collection: Vec<[u8; 32]>,
+    /// Every pattern is checked against each candidate address, in order.
+    pub patterns: Vec<Pattern>,
+    pub optimize: bool,
+    pub case_sensitive: bool,
+    /// Fixed and searched bytes of the salt, the implementation address by default.
+    pub salt_template: SaltTemplate,
+    /// Result file, defaulting to `result.txt` in the project root, or `result.json`,
+    /// `result.jsonl` or `result.csv` for the other formats.
+    pub output: Option<PathBuf>,
+    pub no_file: bool,
+    pub format: OutputFormat,
+    /// Stop after this many results.
+    pub max_results: Option<u64>,
+    /// Stop after this much time.
+    pub timeout: Option<Duration>,
+    /// Stop after checking this many salts.
+    pub max_attempts: Option<u64>,
+    /// Seed for the salt sequence, random if not given.
+    pub seed: Option<u64>,
+    /// File recording how far the search got, resumed from if it exists.
+    pub checkpoint: Option<PathBuf>,
+    pub num_threads: usize,
+    pub use_gpu: bool,
+    /// Search on the CPU and the OpenCL devices at once.
+    pub hybrid: bool,
+    /// OpenCL platform to search on, the default platform if not given.
+    pub platform: Option<DeviceSelector>,
+    /// OpenCL devices to search on at once, the platform's first device if empty.
+    pub devices: Vec<DeviceSelector>,
+    /// Salts per GPU kernel launch, tuned for each device if not given.
+    pub launch_size: Option<usize>,
+    /// GPU work-group size, `0` for the driver's choice, tuned for each device if not given.
+    pub local_size: Option<usize>,
+    /// File remembering each device's tuned launch shape, so later runs skip tuning.
+    pub tune_cache: Option<PathBuf>,
+    /// Tune again even if the tuning cache has the device.
+    pub retune: bool,
+}
+
+/// The chain, NFT contract and token an account is created for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Target {
     pub chain_id: [u8; 32],
     pub nft_address: [u8; 20],
     pub token_id: [u8; 32],
-    pub pattern: String,
-    pub pattern_mode: PatternMode,
-    pub num_threads: usize,
-    pub use_gpu: bool,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PatternMode {
-    Prefix,   // Pattern must be at start of address
-    Contains, // Pattern can be anywhere in address
+    Prefix,           // Pattern must be at start of address
+    Contains,         // Pattern can be anywhere in address
+    Suffix,           // Pattern must be at end of address
+    PrefixSuffix,     // Pattern at start and `suffix` at end of address
+    Mask,             // Pattern is a 40-nibble mask with X wildcards
+    LeadingZeroBytes, // At least `min_score` leading zero bytes
+    TotalZeroBytes,   // At least `min_score` zero bytes anywhere
+    PatternLength,    // At least `min_score` leading nibbles of the prefix pattern
+}
+
+impl PatternMode {
+    /// Whether this mode is checked as an [`AddressMask`].
+    pub fn uses_mask(self) -> bool {
+        matches!(
+            self,
+            PatternMode::Prefix
+                | PatternMode::Suffix
+                | PatternMode::PrefixSuffix
+                | PatternMode::Mask
+                | PatternMode::PatternLength
+        )
+    }
+
+    /// Whether this mode reports a score instead of a textual match.
+    pub fn is_scored(self) -> bool {
+        matches!(
+            self,
+            PatternMode::LeadingZeroBytes
+                | PatternMode::TotalZeroBytes
+                | PatternMode::PatternLength
+        )
+    }
 }
 
 impl Config {
-    pub fn new(mut args: std::env::Args) -> Result<Self, &'static str> {
+    /// Parses command line arguments, starting with the program name.
+    pub fn new(mut args: impl Iterator<Item = String>) -> Result<Self, &'static str> {
         args.next(); // skip program name
 
         let mut resistry_address_string: Option<String> = None;
         let mut implement_address_string: Option<String> = None;
-        let mut chain_id_string: Option<String> = None;
+        let mut chain_id_strings: Vec<String> = Vec::new();
+        let mut target_strings: Vec<String> = Vec::new();
         let mut nft_address_string: Option<String> = None;
         let mut token_id_string: Option<String> = None;
-        let mut pattern = String::new();
-        let mut pattern_mode = PatternMode::Prefix;
+        let mut token_range: Option<String> = None;
+        let mut tokens_file: Option<String> = None;
+        let mut prefixes: Vec<String> = Vec::new();
+        let mut suffixes: Vec<String> = Vec::new();
+        let mut contains: Vec<String> = Vec::new();
+        let mut masks: Vec<String> = Vec::new();
+        let mut leading_zero_bytes: Vec<u32> = Vec::new();
+        let mut total_zero_bytes: Vec<u32> = Vec::new();
+        let mut patterns_file: Option<String> = None;
+        let mut optimize: Option<String> = None;
+        let mut case_sensitive = false;
+        let mut salt_prefix: Option<String> = None;
+        let mut salt_template: Option<SaltTemplate> = None;
+        let mut output: Option<PathBuf> = None;
+        let mut no_file = false;
+        let mut format = OutputFormat::Text;
+        let mut max_results: Option<u64> = None;
+        let mut timeout: Option<Duration> = None;
+        let mut max_attempts: Option<u64> = None;
+        let mut seed: Option<u64> = None;
+        let mut checkpoint: Option<PathBuf> = None;
         let mut num_threads: usize = 0; // 0 = auto (use all cores)
         let mut use_gpu = false;
+        let mut hybrid = false;
+        let mut platform: Option<DeviceSelector> = None;
+        let mut devices: Vec<DeviceSelector> = Vec::new();
+        let mut launch_size: Option<usize> = None;
+        let mut local_size: Option<usize> = None;
+        let mut tune_cache: Option<PathBuf> = None;
+        let mut retune = false;
 
         while let Some(arg) = args.next() {
             match arg.as_str() {
                 "--gpu" | "-g" => {
                     use_gpu = true;
                 }
+                "--hybrid" => {
+                    hybrid = true;
+                }
+                "--platform" => {
+                    platform = Some(
+                        args.next()
+                            .ok_or("--platform requires all, an index or a name")?
+                            .parse()?,
+                    );
+                }
+                "--device" => {
+                    let list = args
+                        .next()
+                        .ok_or("--device requires all, an index or a name")?;
+                    for selector in list.split(',') {
+                        devices.push(selector.parse()?);
+                    }
+                }
+                // --batch-size is the flag's old name
+                "--launch-size" | "--batch-size" => {
+                    let count = args.next().ok_or("--launch-size requires a number")?;
+                    launch_size = Some(
+                        count
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or("--launch-size must be a positive number")?,
+                    );
+                }
+                "--local-size" => {
+                    let size = args.next().ok_or("--local-size requires a number")?;
+                    local_size =
+                        Some(size.parse().map_err(
+                            |_| "--local-size must be a number, 0 for the driver's choice",
+                        )?);
+                }
+                "--tune-cache" => {
+                    tune_cache = Some(args.next().ok_or("--tune-cache requires a path")?.into());
+                }
+                "--retune" => {
+                    retune = true;
+                }
                 "--list-gpus" => {
-                    let _ = crate::list_gpus();
+                    if let Err(err) = crate::list_gpus() {
+                        eprintln!("Could not list OpenCL devices: {err}");
+                        std::process::exit(1);
+                    }
                     std::process::exit(0);
                 }
                 "--workers" | "-w" => {
@@ -83,7 +252,13 @@ impl Config {
                         Some(args.next().ok_or("--implementation requires an address")?);
                 }
                 "--chain" | "-c" => {
-                    chain_id_string = Some(args.next().ok_or("--chain requires a chain ID")?);
+                    chain_id_strings.push(args.next().ok_or("--chain requires a chain ID")?);
+                }
+                "--target" => {
+                    target_strings.push(
+                        args.next()
+                            .ok_or("--target requires <chain>:<nft>:<token>")?,
+                    );
                 }
                 "--nft" | "-n" => {
                     nft_address_string = Some(args.next().ok_or("--nft requires an address")?);
@@ -91,13 +266,114 @@ impl Config {
                 "--token" | "-t" => {
                     token_id_string = Some(args.next().ok_or("--token requires a token ID")?);
                 }
+                "--token-range" => {
+                    token_range = Some(
+                        args.next()
+                            .ok_or("--token-range requires a range such as 1..10000")?,
+                    );
+                }
+                "--tokens-file" => {
+                    tokens_file = Some(args.next().ok_or("--tokens-file requires a path")?);
+                }
                 "--prefix" | "-p" => {
-                    pattern = args.next().ok_or("--prefix requires a pattern")?;
-                    pattern_mode = PatternMode::Prefix;
+                    prefixes.push(args.next().ok_or("--prefix requires a pattern")?);
+                }
+                "--suffix" => {
+                    suffixes.push(args.next().ok_or("--suffix requires a pattern")?);
                 }
                 "--contains" => {
-                    pattern = args.next().ok_or("--contains requires a pattern")?;
-                    pattern_mode = PatternMode::Contains;
+                    contains.push(args.next().ok_or("--contains requires a pattern")?);
+                }
+                "--mask" => {
+                    masks.push(args.next().ok_or("--mask requires a 40-nibble pattern")?);
+                }
+                "--patterns-file" => {
+                    patterns_file = Some(args.next().ok_or("--patterns-file requires a path")?);
+                }
+                "--leading-zero-bytes" => {
+                    let count = args
+                        .next()
+                        .ok_or("--leading-zero-bytes requires a number")?;
+                    leading_zero_bytes.push(
+                        count
+                            .parse()
+                            .map_err(|_| "--leading-zero-bytes must be a number from 1 to 20")?,
+                    );
+                }
+                "--total-zero-bytes" => {
+                    let count = args.next().ok_or("--total-zero-bytes requires a number")?;
+                    total_zero_bytes.push(
+                        count
+                            .parse()
+                            .map_err(|_| "--total-zero-bytes must be a number from 1 to 20")?,
+                    );
+                }
+                "--case-sensitive" => {
+                    case_sensitive = true;
+                }
+                "--salt-prefix" => {
+                    salt_prefix = Some(args.next().ok_or("--salt-prefix requires hex bytes")?);
+                }
+                "--salt-template" => {
+                    salt_template = Some(
+                        args.next()
+                            .ok_or("--salt-template requires a template")?
+                            .parse()?,
+                    );
+                }
+                "--output" | "-o" => {
+                    output = Some(args.next().ok_or("--output requires a path")?.into());
+                }
+                "--no-file" => {
+                    no_file = true;
+                }
+                "--format" => {
+                    format = args
+                        .next()
+                        .ok_or("--format requires text, json, jsonl or csv")?
+                        .parse()?;
+                }
+                "--max-results" => {
+                    let count = args.next().ok_or("--max-results requires a number")?;
+                    max_results = Some(
+                        count
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or("--max-results must be a positive number")?,
+                    );
+                }
+                "--timeout" => {
+                    let duration = args.next().ok_or("--timeout requires a duration")?;
+                    timeout = Some(
+                        parse_duration(&duration)
+                            .ok_or("--timeout must be a duration such as 90, 90s, 15m, 2h or 1d")?,
+                    );
+                }
+                "--max-attempts" => {
+                    let count = args.next().ok_or("--max-attempts requires a number")?;
+                    max_attempts = Some(
+                        count
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or("--max-attempts must be a positive number")?,
+                    );
+                }
+                "--seed" => {
+                    let value = args.next().ok_or("--seed requires a number")?;
+                    seed =
+                        Some(parse_u64(&value).ok_or(
+                            "--seed must be a 64-bit number, in decimal or 0x-prefixed hex",
+                        )?);
+                }
+                "--checkpoint" => {
+                    checkpoint = Some(args.next().ok_or("--checkpoint requires a path")?.into());
+                }
+                "--optimize" => {
+                    optimize = Some(args.next().ok_or(
+                        "--optimize requires leading-zeros, zero-bytes or pattern-length",
+                    )?);
                 }
                 _ => {
                     return Err("Unknown argument. Use --help for usage.");
@@ -105,237 +381,690 @@ impl Config {
             }
         }
 
+        if (platform.is_some() || !devices.is_empty()) && !use_gpu && !hybrid {
+            return Err("--platform and --device require --gpu or --hybrid");
+        }
+        if (launch_size.is_some() || local_size.is_some() || tune_cache.is_some() || retune)
+            && !use_gpu
+            && !hybrid
+        {
+            return Err(
+                "--launch-size, --local-size, --tune-cache and --retune require --gpu or --hybrid",
+            );
+        }
+        if retune && tune_cache.is_none() {
+            return Err("--retune requires --tune-cache");
+        }
+
         let resistry_address_string = resistry_address_string;
         let implement_address_string =
             implement_address_string.ok_or("Missing --implementation argument")?;
-        let chain_id_string = chain_id_string.ok_or("Missing --chain argument")?;
-        let nft_address_string = nft_address_string.ok_or("Missing --nft argument")?;
-        let token_id_string = token_id_string.ok_or("Missing --token argument")?;
+        if chain_id_strings.is_empty() && target_strings.is_empty() {
+            return Err("Missing --chain argument");
+        }
+        if [&token_id_string, &token_range, &tokens_file]
+            .iter()
+            .filter(|token| token.is_some())
+            .count()
+            > 1
+        {
+            return Err("Use only one of --token, --token-range and --tokens-file");
+        }
+        let collection = match (token_range, tokens_file) {
+            (Some(range), _) => parse_token_range(&range)?,
+            (_, Some(path)) => {
+                let contents =
+                    std::fs::read_to_string(path).map_err(|_| "could not read --tokens-file")?;
+                parse_token_list(&contents)?
+            }
+            _ => Vec::new(),
+        };
+        if chain_id_strings.is_empty() {
+            if nft_address_string.is_some() || token_id_string.is_some() || !collection.is_empty() {
+                return Err("--nft and --token require a --chain");
+            }
+        } else {
+            if nft_address_string.is_none() {
+                return Err("Missing --nft argument");
+            }
+            if collection.is_empty() && token_id_string.is_none() {
+                return Err("Missing --token argument");
+            }
+        }
+        if !collection.is_empty() && (chain_id_strings.len() > 1 || !target_strings.is_empty()) {
+            return Err("--token-range and --tokens-file take a single --chain and no --target");
+        }
+        if !collection.is_empty() && optimize.is_some() {
+            return Err("--optimize cannot be combined with --token-range or --tokens-file");
+        }
 
-        if pattern.is_empty() {
-            return Err("Missing pattern. Use --prefix or --contains to specify a pattern.");
+        let mut patterns = Vec::new();
+        // A single --prefix and --suffix combine into one pattern; repeated ones are separate
+        if let ([prefix], [suffix]) = (prefixes.as_slice(), suffixes.as_slice()) {
+            patterns.push(Pattern::new(PatternMode::PrefixSuffix, prefix, suffix, 0)?);
+        } else {
+            for prefix in &prefixes {
+                patterns.push(Pattern::new(PatternMode::Prefix, prefix, "", 0)?);
+            }
+            for suffix in &suffixes {
+                patterns.push(Pattern::new(PatternMode::Suffix, suffix, "", 0)?);
+            }
+        }
+        for pattern in &contains {
+            patterns.push(Pattern::new(PatternMode::Contains, pattern, "", 0)?);
+        }
+        for mask in &masks {
+            patterns.push(Pattern::new(PatternMode::Mask, mask, "", 0)?);
+        }
+        for count in leading_zero_bytes {
+            patterns.push(Pattern::new(PatternMode::LeadingZeroBytes, "", "", count)?);
+        }
+        for count in total_zero_bytes {
+            patterns.push(Pattern::new(PatternMode::TotalZeroBytes, "", "", count)?);
+        }
+        if let Some(path) = patterns_file {
+            let contents =
+                std::fs::read_to_string(path).map_err(|_| "could not read --patterns-file")?;
+            for pattern in contents.lines().filter_map(Pattern::parse_line) {
+                patterns.push(pattern?);
+            }
         }
 
-        // Use default ERC6551 Registry if not specified
-        let resistry_address: [u8; 20] = if let Some(addr_str) = resistry_address_string {
-            let Ok(vec) = hex::decode(addr_str) else {
-                return Err("could not decode registry address argument");
+        if let Some(target) = optimize.as_deref() {
+            let pattern = match (target, patterns.as_slice()) {
+                ("leading-zeros", []) => Pattern::new(PatternMode::LeadingZeroBytes, "", "", 1)?,
+                ("zero-bytes", []) => Pattern::new(PatternMode::TotalZeroBytes, "", "", 1)?,
+                ("leading-zeros" | "zero-bytes", _) => {
+                    return Err(
+                        "--optimize leading-zeros/zero-bytes cannot be combined with a pattern",
+                    );
+                }
+                ("pattern-length", [pattern]) if pattern.mode == PatternMode::Prefix => {
+                    Pattern::new(PatternMode::PatternLength, &pattern.pattern, "", 1)?
+                }
+                ("pattern-length", _) => {
+                    return Err("--optimize pattern-length requires a single --prefix pattern");
+                }
+                _ => {
+                    return Err("--optimize must be leading-zeros, zero-bytes or pattern-length");
+                }
             };
-            vec.try_into().map_err(|_| "invalid length for registry address")?  
-        } else {
-            ERC6551_REGISTRY
-        };
-        
-        let Ok(implement_address_vec) = hex::decode(implement_address_string) else {
-            return Err("could not decode implement address argument");
-        };
-        let Ok(nft_address_vec) = hex::decode(nft_address_string) else {
-            return Err("could not decode nft address argument");
-        };
-        let Ok(implement_address) = implement_address_vec.try_into() else {
-            return Err("invalid length for implement address argument");
-        };
-        let Ok(chain_id) = chain_id_string.parse::<u128>() else {
-            return Err("could not parse chain id as decimal integer");
-        };
-        let chain_id_bytes: [u8; 16] = chain_id.to_be_bytes();
-        let mut chain_id_vec = [0u8; 32];
-        chain_id_vec[16..].copy_from_slice(&chain_id_bytes);
-        let Ok(nft_address) = nft_address_vec.try_into() else {
-            return Err("invalid length for nft address argument");
+            patterns = vec![pattern];
+        }
+        if salt_prefix.is_some() && salt_template.is_some() {
+            return Err("--salt-prefix cannot be combined with --salt-template");
+        }
+        if no_file && output.is_some() {
+            return Err("--no-file cannot be combined with --output");
+        }
+        if patterns.is_empty() {
+            return Err(
+                "Missing pattern. Use --prefix, --suffix, --contains, --mask, --patterns-file or a zero-byte score.",
+            );
+        }
+        if case_sensitive && patterns.iter().all(|pattern| pattern.pattern.is_empty()) {
+            return Err("--case-sensitive requires a hex pattern");
+        }
+
+        // Use default ERC6551 Registry if not specified
+        let resistry_address = match resistry_address_string {
+            Some(address) => {
+                parse_address(&address).ok_or("could not decode registry address argument")?
+            }
+            None => ERC6551_REGISTRY,
         };
-        let Ok(token_id) = token_id_string.parse::<u128>() else {
-            return Err("could not parse token id as decimal integer");
+        let implement_address = parse_address(&implement_address_string)
+            .ok_or("could not decode implement address argument")?;
+        let salt_template = match (salt_prefix, salt_template) {
+            (Some(prefix), _) => SaltTemplate::from_prefix(&prefix)?,
+            (None, Some(template)) => template,
+            (None, None) => SaltTemplate::implementation(implement_address),
         };
-        let token_id_bytes: [u8; 16] = token_id.to_be_bytes();
-        let mut token_id_vec = [0u8; 32];
-        token_id_vec[16..].copy_from_slice(&token_id_bytes);
+
+        // Every --chain shares the --nft and --token; --target adds any other combination
+        let mut targets = Vec::new();
+        if let Some(nft_address_string) = nft_address_string {
+            let nft_address = parse_address(&nft_address_string)
+                .ok_or("could not decode nft address argument")?;
+            let token_id = match (token_id_string, collection.first()) {
+                (Some(token_id), _) => parse_token_id(&token_id)?,
+                (None, Some(first)) => *first,
+                (None, None) => unreachable!("checked above"),
+            };
+            for chain_id in &chain_id_strings {
+                targets.push(Target {
+                    chain_id: parse_chain_id(chain_id)?,
+                    nft_address,
+                    token_id,
+                });
+            }
+        }
+        for target in &target_strings {
+            targets.push(parse_target(target)?);
+        }
+        let mut seen = std::collections::HashSet::new();
+        if !targets.iter().all(|target| seen.insert(target)) {
+            return Err("the same chain, NFT and token are given more than once");
+        }
 
         Ok(Self {
             resistry_address,
             implement_address,
-            chain_id: chain_id_vec,
-            nft_address,
-            token_id: token_id_vec,
-            pattern,
-            pattern_mode,
+            targets,
+            collection,
+            patterns,
+            optimize: optimize.is_some(),
+            case_sensitive,
+            salt_template,
+            output,
+            no_file,
+            format,
+            max_results,
+            timeout,
+            max_attempts,
+            seed,
+            checkpoint,
             num_threads,
             use_gpu,
+            hybrid,
+            platform,
+            devices,
+            launch_size,
+            local_size,
+            tune_cache,
+            retune,
         })
     }
+
+    /// Every account searched: the targets, or one per token of the collection.
+    pub(crate) fn search_targets(&self) -> Vec<Target> {
+        if self.collection.is_empty() {
+            return self.targets.clone();
+        }
+        self.collection
+            .iter()
+            .map(|token_id| Target {
+                token_id: *token_id,
+                ..self.targets[0]
+            })
+            .collect()
+    }
+
+    /// The [`search_targets`](Self::search_targets) checked at step `step` of the search: all
+    /// of them, or a single token of the collection. `None` once every step is done.
+    pub(crate) fn step_targets(&self, step: usize) -> Option<Range<usize>> {
+        if self.collection.is_empty() {
+            (step == 0).then_some(0..self.targets.len())
+        } else {
+            (step < self.collection.len()).then_some(step..step + 1)
+        }
+    }
 }
 
-pub fn cpu(config: Config) -> Result<(), Box<dyn Error>> {
-    // Set thread pool size
-    let num_threads = if config.num_threads > 0 {
-        config.num_threads
-    } else {
-        rayon::current_num_threads()
+/// Parses a duration given in seconds, optionally suffixed with `s`, `m`, `h` or `d`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (digits, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => value.split_at(split),
+        None => (value, "s"),
+    };
+    let seconds_per_unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
     };
+    let count: u64 = digits.parse().ok().filter(|count| *count > 0)?;
+    Some(Duration::from_secs(count.checked_mul(seconds_per_unit)?))
+}
+
+/// Parses a 20-byte address in hex, with or without `0x`.
+pub(crate) fn parse_address(value: &str) -> Option<[u8; 20]> {
+    hex::decode(value).ok()?.try_into().ok()
+}
+
+pub(crate) fn parse_chain_id(value: &str) -> Result<[u8; 32], &'static str> {
+    parse_uint256(value).map_err(|e| match e {
+        UintParseError::Invalid => "could not parse chain id as decimal or 0x-prefixed hex integer",
+        UintParseError::Overflow => "chain id does not fit in 256 bits",
+    })
+}
+
+/// Parses `<chain>:<nft>:<token>`.
+fn parse_target(value: &str) -> Result<Target, &'static str> {
+    let [chain_id, nft_address, token_id] = value
+        .split(':')
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| "--target must be <chain>:<nft>:<token>")?;
+    let nft_address =
+        parse_address(nft_address).ok_or("could not decode the nft address of a --target")?;
+    Ok(Target {
+        chain_id: parse_chain_id(chain_id)?,
+        nft_address,
+        token_id: parse_token_id(token_id)?,
+    })
+}
 
-    if config.num_threads > 0 {
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(num_threads)
-            .build_global()
-            .ok(); // Ignore error if already initialized
+pub(crate) fn parse_token_id(value: &str) -> Result<[u8; 32], &'static str> {
+    parse_uint256(value).map_err(|e| match e {
+        UintParseError::Invalid => "could not parse token id as decimal or 0x-prefixed hex integer",
+        UintParseError::Overflow => "token id does not fit in 256 bits",
+    })
+}
+
+/// Parses `first..last`, including both ends.
+fn parse_token_range(range: &str) -> Result<Vec<[u8; 32]>, &'static str> {
+    let (first, last) = range
+        .split_once("..")
+        .ok_or("--token-range must be a range such as 1..10000")?;
+    let first = U256::from_be_bytes(parse_token_id(first)?);
+    let last = U256::from_be_bytes(parse_token_id(last)?);
+    if last < first {
+        return Err("--token-range must not end before it starts");
+    }
+    if last - first >= U256::from(MAX_COLLECTION_SIZE) {
+        return Err("--token-range covers too many tokens (at most 1048576)");
+    }
+    let count = (last - first).to::<usize>() + 1;
+    Ok((0..count)
+        .map(|i| (first + U256::from(i)).to_be_bytes())
+        .collect())
+}
+
+/// Parses one token ID per line, skipping blank lines and `#` comments.
+fn parse_token_list(contents: &str) -> Result<Vec<[u8; 32]>, &'static str> {
+    let tokens = contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_token_id)
+        .collect::<Result<Vec<_>, _>>()?;
+    if tokens.is_empty() {
+        return Err("--tokens-file contains no token IDs");
     }
+    if tokens.len() > MAX_COLLECTION_SIZE {
+        return Err("--tokens-file lists too many tokens (at most 1048576)");
+    }
+    // Each token is searched from the start of the sequence, so a repeat finds the same salt
+    let mut seen = std::collections::HashSet::new();
+    if !tokens.iter().all(|token| seen.insert(token)) {
+        return Err("--tokens-file lists the same token more than once");
+    }
+    Ok(tokens)
+}
+
+fn parse_u64(value: &str) -> Option<u64> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(digits) => u64::from_str_radix(digits, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
 
-    println!("🧵 Using {} threads", num_threads);
-
-    let file = Arc::new(Mutex::new(output_file()));
-    let mut header_bytes_code_header = [0; 55];
-    header_bytes_code_header[0..20].copy_from_slice(&ERC6551_CONSTRUCTOR_HEADER);
-    header_bytes_code_header[20..40].copy_from_slice(&config.implement_address);
-    header_bytes_code_header[40..].copy_from_slice(&ERC6551_FOOTER);
-    let mut header_bytes_code_body = [0; 6];
-    let mut header_bytes_code_footer = [0; 96];
-    header_bytes_code_footer[0..32].copy_from_slice(&config.chain_id);
-    header_bytes_code_footer[44..64].copy_from_slice(&config.nft_address);
-    header_bytes_code_footer[64..].copy_from_slice(&config.token_id);
-
-    // Parse pattern - convert to lowercase hex bytes for matching
-    let pattern = config.pattern.to_lowercase();
-    let pattern = pattern.strip_prefix("0x").unwrap_or(&pattern);
-    let pattern_mode = config.pattern_mode;
-
-    match pattern_mode {
-        PatternMode::Prefix => println!("🔍 Searching for addresses starting with: 0x{}", pattern),
-        PatternMode::Contains => println!("🔍 Searching for addresses containing: 0x{}", pattern),
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum UintParseError {
+    Invalid,
+    Overflow,
+}
+
+/// Parses a uint256 given in decimal or `0x`-prefixed hex into 32 big-endian bytes.
+pub(crate) fn parse_uint256(value: &str) -> Result<[u8; 32], UintParseError> {
+    // ruint also accepts `_` separators, which would let typos through as other numbers
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(digits) if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            U256::from_str_radix(digits, 16)
+        }
+        None if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+            U256::from_str_radix(value, 10)
+        }
+        _ => return Err(UintParseError::Invalid),
+    };
+    match parsed {
+        Ok(value) => Ok(value.to_be_bytes()),
+        Err(ruint::ParseError::BaseConvertError(ruint::BaseConvertError::Overflow)) => {
+            Err(UintParseError::Overflow)
+        }
+        Err(_) => Err(UintParseError::Invalid),
     }
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-
-    // Speed tracking with Arc for thread safety
-    let total_checked = Arc::new(AtomicU64::new(0));
-    let found_count = Arc::new(AtomicU64::new(0));
-    let start_time = Instant::now();
-
-    // Spawn a thread to print speed stats
-    let total_checked_clone = Arc::clone(&total_checked);
-    let found_count_clone = Arc::clone(&found_count);
-    std::thread::spawn(move || {
-        use std::io::Write;
-        let mut last_count = 0u64;
-        loop {
-            std::thread::sleep(std::time::Duration::from_secs(2));
-            let current = total_checked_clone.load(Ordering::Relaxed);
-            let found = found_count_clone.load(Ordering::Relaxed);
-            let elapsed = start_time.elapsed().as_secs_f64();
-            let speed = current as f64 / elapsed;
-            let instant_speed = (current - last_count) as f64 / 2.0;
-            last_count = current;
-
-            eprint!(
-                "\r⚡ Speed: {:.2}M/s | Avg: {:.2}M/s | Checked: {}M | Found: {} | Time: {:.0}s    ",
-                instant_speed / 1_000_000.0,
-                speed / 1_000_000.0,
-                current / 1_000_000,
-                found,
-                elapsed
+}
+
+/// Writes `contents` to `<path>.tmp` and renames it over `path`, so the file is replaced
+/// whole and a crash or kill part-way leaves the old one. The name keeps the whole of `path`
+/// rather than replacing its extension, which could clobber a sibling file.
+pub(crate) fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let mut temp = std::fs::File::create(&temp_path)?;
+    temp.write_all(contents.as_bytes())?;
+    temp.sync_all()?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Searches on the CPU, see [`CpuBackend`].
+pub fn cpu(config: Config) -> Result<RunSummary, Box<dyn Error>> {
+    search(&config, &mut CpuBackend::new(config.num_threads))
+}
+
+/// The init code before the salt: constructor header, implementation and proxy footer.
+pub(crate) fn bytecode_header(implementation: &[u8; 20]) -> [u8; 55] {
+    let mut header = [0; 55];
+    header[0..20].copy_from_slice(&ERC6551_CONSTRUCTOR_HEADER);
+    header[20..40].copy_from_slice(implementation);
+    header[40..].copy_from_slice(&ERC6551_FOOTER);
+    header
+}
+
+/// The init code after the salt: chain ID, padded NFT address and token ID.
+pub(crate) fn bytecode_footer(target: &Target) -> [u8; 96] {
+    let mut footer = [0; 96];
+    footer[0..32].copy_from_slice(&target.chain_id);
+    footer[44..64].copy_from_slice(&target.nft_address);
+    footer[64..].copy_from_slice(&target.token_id);
+    footer
+}
+
+/// Finds the first pattern `address` matches, along with its score.
+///
+/// Only candidates that already match case-insensitively pay for the EIP-55 checksum.
+pub(crate) fn first_match<'a>(config: &'a Config, address: &Address) -> Option<(&'a Pattern, u32)> {
+    config.patterns.iter().find_map(|pattern| {
+        let score = pattern.matches(address.as_slice())?;
+        if !config.case_sensitive {
+            return Some((pattern, score));
+        }
+        pattern
+            .checksum_score(address, score)
+            .map(|score| (pattern, score))
+    })
+}
+
+pub(crate) fn print_pattern(config: &Config) {
+    if config.targets.len() > 1 {
+        println!(
+            "🎯 Checking {} targets for every salt:",
+            config.targets.len()
+        );
+        for target in &config.targets {
+            println!(
+                "   chain {}, nft {}, token {}",
+                U256::from_be_bytes(target.chain_id),
+                Address::from(target.nft_address),
+                U256::from_be_bytes(target.token_id)
             );
-            let _ = std::io::stderr().flush();
-        }
-    });
-
-    loop {
-        let mut header = [0; 47];
-        header[0] = CONTROL_CHARACTER;
-        header[1..21].copy_from_slice(&config.resistry_address);
-        header[21..41].copy_from_slice(&config.implement_address);
-        header[41..].copy_from_slice(&FixedBytes::<6>::random()[..]);
-        header_bytes_code_body.copy_from_slice(&header[41..]);
-        let mut hash_header = Keccak::v256();
-        hash_header.update(&header);
-        (0..MAX_INCREMENTER).into_par_iter().for_each(|salt| {
-            let salt = salt.to_le_bytes();
-            let salt_incremented_segment = &salt[..6];
-            let mut hash = hash_header.clone();
-            let mut hash_bytescode = Keccak::v256();
-            hash_bytescode.update(&header_bytes_code_header);
-            hash_bytescode.update(&config.implement_address);
-            hash_bytescode.update(&header_bytes_code_body);
-            hash_bytescode.update(&salt_incremented_segment);
-            hash_bytescode.update(&header_bytes_code_footer);
-            let mut keccak_bytescode: [u8; 32] = [0; 32];
-            hash_bytescode.finalize(&mut keccak_bytescode);
-            hash.update(salt_incremented_segment);
-            hash.update(&keccak_bytescode);
-            let mut keccak_create2: [u8; 32] = [0; 32];
-            hash.finalize(&mut keccak_create2);
-            let address = <&Address>::try_from(&keccak_create2[12..]).unwrap();
-
-            total_checked.fetch_add(1, Ordering::Relaxed);
-
-            // Check pattern match based on mode
-            match pattern_mode {
-                PatternMode::Prefix => {
-                    let addr_hex = hex::encode(address); // already lowercase
-                    if !addr_hex.starts_with(pattern) {
-                        return;
-                    }
-                    // Pattern matched at prefix!
-                    found_count.fetch_add(1, Ordering::Relaxed);
-                    let header_hex_string = hex::encode(header);
-                    let body_hex_string = hex::encode(salt_incremented_segment);
-                    let full_salt = format!("0x{}{}", &header_hex_string[42..], &body_hex_string);
-                    let output = format!("\n{} => 0x{}\n", full_salt, addr_hex);
-                    print!("{output}");
-                    let _ = stdout().flush();
-                    {
-                        let mut f = file.lock().unwrap();
-                        writeln!(f, "{} => 0x{}", full_salt, addr_hex)
-                            .expect("Couldn't write to `result.txt` file.");
-                    }
-                    return;
-                }
-                PatternMode::Contains => {
-                    let addr_hex = hex::encode(address); // already lowercase
-                    if !addr_hex.contains(pattern) {
-                        return;
-                    }
-                    // Pattern found in address!
-                    found_count.fetch_add(1, Ordering::Relaxed);
-                    let header_hex_string = hex::encode(header);
-                    let body_hex_string = hex::encode(salt_incremented_segment);
-                    let full_salt = format!("0x{}{}", &header_hex_string[42..], &body_hex_string);
-                    let output = format!("\n{} => 0x{}\n", full_salt, addr_hex);
-                    print!("{output}");
-                    let _ = stdout().flush();
-                    {
-                        let mut f = file.lock().unwrap();
-                        writeln!(f, "{} => 0x{}", full_salt, addr_hex)
-                            .expect("Couldn't write to `result.txt` file.");
-                    }
-                }
+        }
+    }
+    if config.optimize {
+        let pattern = &config.patterns[0];
+        match pattern.mode {
+            PatternMode::LeadingZeroBytes => println!("🏆 Optimizing for most leading zero bytes"),
+            PatternMode::TotalZeroBytes => println!("🏆 Optimizing for most zero bytes"),
+            _ => println!("🏆 Optimizing for longest prefix of: 0x{}", pattern.pattern),
+        }
+        return;
+    }
+    if config.patterns.len() > 1 {
+        println!(
+            "🔍 Checking {} patterns in one pass:",
+            config.patterns.len()
+        );
+    }
+    for pattern in &config.patterns {
+        let (suffix, min_score) = (&pattern.suffix, pattern.min_score);
+        let pattern_text = &pattern.pattern;
+        match pattern.mode {
+            PatternMode::Prefix => {
+                println!(
+                    "🔍 Searching for addresses starting with: 0x{}",
+                    pattern_text
+                )
             }
-        });
+            PatternMode::Contains => {
+                println!("🔍 Searching for addresses containing: 0x{}", pattern_text)
+            }
+            PatternMode::Suffix => {
+                println!("🔍 Searching for addresses ending with: {}", pattern_text)
+            }
+            PatternMode::PrefixSuffix => println!(
+                "🔍 Searching for addresses starting with: 0x{} and ending with: {}",
+                pattern_text, suffix
+            ),
+            PatternMode::Mask => {
+                println!(
+                    "🔍 Searching for addresses matching mask: 0x{}",
+                    pattern_text
+                )
+            }
+            PatternMode::LeadingZeroBytes => println!(
+                "🔍 Searching for addresses with at least {} leading zero bytes",
+                min_score
+            ),
+            PatternMode::TotalZeroBytes => println!(
+                "🔍 Searching for addresses with at least {} zero bytes",
+                min_score
+            ),
+            PatternMode::PatternLength => println!(
+                "🔍 Searching for addresses starting with at least {} nibbles of: 0x{}",
+                min_score, pattern_text
+            ),
+        }
+    }
+    if !config.collection.is_empty() {
+        println!(
+            "🪙 Collection: {} tokens, one result each",
+            config.collection.len()
+        );
     }
 }
 
-#[track_caller]
-fn output_file() -> File {
-    // Always save to executable's parent directory (project root when running from target/release)
-    let result_path = std::env::current_exe()
-        .ok()
-        .and_then(|exe| exe.parent().map(|p| p.to_path_buf()))
-        .and_then(|dir| {
-            // If running from target/release, go up 2 levels to project root
-            if dir.ends_with("release") || dir.ends_with("debug") {
-                dir.parent()
-                    .and_then(|p| p.parent())
-                    .map(|p| p.to_path_buf())
-            } else {
-                Some(dir)
-            }
-        })
-        .map(|dir| dir.join("result.txt"))
-        .unwrap_or_else(|| std::path::PathBuf::from("result.txt"));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uint256(value: u64) -> [u8; 32] {
+        U256::from(value).to_be_bytes()
+    }
+
+    #[test]
+    fn parse_uint256_reads_decimal_and_hex() {
+        assert_eq!(parse_uint256("0"), Ok(uint256(0)));
+        assert_eq!(parse_uint256("1000"), Ok(uint256(1000)));
+        assert_eq!(parse_uint256("0x3e8"), Ok(uint256(1000)));
+        assert_eq!(parse_uint256("0X3E8"), Ok(uint256(1000)));
+        assert_eq!(parse_uint256("0010"), Ok(uint256(10)));
+    }
+
+    #[test]
+    fn parse_uint256_accepts_the_largest_value_and_no_more() {
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        assert_eq!(parse_uint256(max), Ok([0xff; 32]));
+        assert_eq!(
+            parse_uint256(&format!("0x{}", "f".repeat(64))),
+            Ok([0xff; 32])
+        );
+
+        let past_max =
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936";
+        assert_eq!(parse_uint256(past_max), Err(UintParseError::Overflow));
+        assert_eq!(
+            parse_uint256(&format!("0x1{}", "0".repeat(64))),
+            Err(UintParseError::Overflow)
+        );
+    }
+
+    #[test]
+    fn parse_uint256_rejects_anything_but_digits() {
+        for value in [
+            "", "0x", "1_000", "0x1_000", "-1", "+1", " 1", "1 ", "1e3", "abc", "0xg", "0b101",
+        ] {
+            assert_eq!(
+                parse_uint256(value),
+                Err(UintParseError::Invalid),
+                "{value:?}"
+            );
+        }
+    }
 
-    println!("📁 Saving results to: {}", result_path.display());
+    const NFT: &str = "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D";
 
-    OpenOptions::new()
-        .append(true)
-        .create(true)
-        .read(true)
-        .open(&result_path)
-        .expect("Could not create or open `result.txt` file.")
+    #[test]
+    fn parse_target_reads_chain_nft_and_token() {
+        assert_eq!(
+            parse_target(&format!("8453:{NFT}:0x2a")),
+            Ok(Target {
+                chain_id: uint256(8453),
+                nft_address: parse_address(NFT).unwrap(),
+                token_id: uint256(42),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_target_rejects_malformed_tuples() {
+        for value in [
+            String::new(),
+            "1".to_string(),
+            format!("1:{NFT}"),
+            format!("1:{NFT}:1:1"),
+            format!("1,{NFT},1"),
+        ] {
+            assert_eq!(
+                parse_target(&value),
+                Err("--target must be <chain>:<nft>:<token>"),
+                "{value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_target_rejects_bad_fields() {
+        assert_eq!(
+            parse_target("1:0x1234:1"),
+            Err("could not decode the nft address of a --target")
+        );
+        assert_eq!(
+            parse_target("1:nft:1"),
+            Err("could not decode the nft address of a --target")
+        );
+        assert!(parse_target(&format!("one:{NFT}:1")).is_err());
+        assert!(parse_target(&format!("1:{NFT}:-1")).is_err());
+        assert!(parse_target(&format!("1:{NFT}:")).is_err());
+        let past_max = format!("0x1{}", "0".repeat(64));
+        assert_eq!(
+            parse_target(&format!("1:{NFT}:{past_max}")),
+            Err("token id does not fit in 256 bits")
+        );
+    }
+
+    #[test]
+    fn parse_token_range_includes_both_ends() {
+        assert_eq!(
+            parse_token_range("1..3"),
+            Ok(vec![uint256(1), uint256(2), uint256(3)])
+        );
+        assert_eq!(parse_token_range("0x10..16"), Ok(vec![uint256(16)]));
+        let max = format!("0x{}", "f".repeat(64));
+        assert_eq!(
+            parse_token_range(&format!("{max}..{max}")),
+            Ok(vec![[0xff; 32]])
+        );
+    }
+
+    #[test]
+    fn parse_token_range_rejects_reversed_oversized_and_malformed_ranges() {
+        assert!(parse_token_range("3..1").is_err());
+        assert!(parse_token_range("1-3").is_err());
+        assert!(parse_token_range("1..").is_err());
+        assert!(parse_token_range("1..x").is_err());
+
+        let last = MAX_COLLECTION_SIZE as u64;
+        assert_eq!(
+            parse_token_range(&format!("1..{last}")).map(|tokens| tokens.len()),
+            Ok(MAX_COLLECTION_SIZE)
+        );
+        assert!(parse_token_range(&format!("0..{last}")).is_err());
+    }
+
+    #[test]
+    fn parse_token_list_skips_blank_and_comment_lines() {
+        let contents = "# tokens to search\n1\n\n  0x2  # hex\n   \n3\n";
+        assert_eq!(
+            parse_token_list(contents),
+            Ok(vec![uint256(1), uint256(2), uint256(3)])
+        );
+    }
+
+    #[test]
+    fn parse_token_list_rejects_empty_duplicate_and_invalid_lists() {
+        assert!(parse_token_list("").is_err());
+        assert!(parse_token_list("# nothing\n\n").is_err());
+        assert!(parse_token_list("1\n2\n1\n").is_err());
+        assert!(parse_token_list("1\n0x1\n").is_err());
+        assert!(parse_token_list("1\ntwo\n").is_err());
+        assert!(parse_token_list("1, 2\n").is_err());
+    }
+
+    #[test]
+    fn parse_duration_reads_each_unit() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(
+            parse_duration("1d"),
+            Some(Duration::from_secs(24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn parse_duration_rejects_zero_overflow_and_garbage() {
+        let overflow = format!("{}d", u64::MAX / 60);
+        for value in [
+            "0",
+            "0s",
+            "",
+            "s",
+            "abc",
+            "1.5h",
+            "-1",
+            " 1s",
+            "1 s",
+            "1w",
+            "1hh",
+            "18446744073709551616",
+            &overflow,
+        ] {
+            assert_eq!(parse_duration(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn batch_size_is_an_alias_of_launch_size() {
+        let config = |size_flag: &str| {
+            let args = [
+                "erc6551crunch",
+                "-i",
+                "0x55266d75D1a14E4572138116aF39863Ed6596E7F",
+                "-c",
+                "1",
+                "-n",
+                "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D",
+                "-t",
+                "1",
+                "-p",
+                "00",
+                "--gpu",
+                size_flag,
+                "4096",
+            ];
+            Config::new(args.into_iter().map(String::from)).unwrap()
+        };
+        assert_eq!(config("--launch-size").launch_size, Some(4096));
+        assert_eq!(config("--batch-size").launch_size, Some(4096));
+    }
 }
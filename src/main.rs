@@ -1,7 +1,10 @@
-use erc6551crunch::Config;
+use erc6551crunch::{Config, StopReason, VerifyConfig};
 use std::env;
 use std::process;
 
+/// Exit status when a search is stopped by SIGINT or SIGTERM (128 + SIGINT).
+const EXIT_INTERRUPTED: i32 = 130;
+
 fn main() {
     println!("╔══════════════════════════════════════════════════════════════╗");
     println!("║           ERC6551 Vanity Address Cruncher                    ║");
@@ -10,24 +13,93 @@ fn main() {
 
     let args: Vec<String> = env::args().collect();
 
+    if args.get(1).map(String::as_str) == Some("verify") {
+        run_verify(&args);
+    }
+
     if args.len() < 2 || args.contains(&"--help".to_string()) || args.contains(&"-h".to_string()) {
         println!("Usage: erc6551crunch [OPTIONS]");
         println!();
         println!("Required Arguments:");
         println!("  -i, --impl <address>           Implementation contract address");
-        println!("  -c, --chain <id>               Chain ID (decimal)");
+        println!("  -c, --chain <id>               Chain ID (uint256, decimal or 0x hex);");
+        println!("                                 repeat to check every chain for each salt");
         println!("  -n, --nft <address>            NFT contract address");
-        println!("  -t, --token <id>               Token ID (decimal)");
+        println!("  -t, --token <id>               Token ID (uint256, decimal or 0x hex)");
+        println!("      OR --token-range <a..b>    Every token from a to b, one result each");
+        println!("      OR --tokens-file <path>    Token IDs from a file, one per line");
+        println!("      --target <chain>:<nft>:<token>");
+        println!("                                 Another account to check for each salt");
+        println!("                                 (repeatable, with or without -c/-n/-t)");
         println!("  -p, --prefix <pattern>         Search for addresses STARTING with pattern");
+        println!("      --suffix <pattern>         Search for addresses ENDING with pattern");
+        println!("                                 (may be combined with --prefix)");
         println!("      OR --contains <pattern>    Search for addresses CONTAINING pattern");
+        println!("      OR --mask <pattern>        Match a 40-nibble mask, X = any nibble");
+        println!("      OR --leading-zero-bytes <n> Addresses with at least n leading zero bytes");
+        println!("      OR --total-zero-bytes <n>  Addresses with at least n zero bytes");
+        println!("      --patterns-file <path>     Read patterns from a file, one per line");
+        println!("                                 (pattern flags may be repeated; all patterns");
+        println!("                                 are checked in one pass and results tagged)");
+        println!();
+        println!("Optimize Mode (runs until stopped, reporting only strictly better results):");
+        println!("      --optimize leading-zeros   Most leading zero bytes");
+        println!("      --optimize zero-bytes      Most zero bytes anywhere");
+        println!("      --optimize pattern-length  Longest match of the --prefix pattern");
+        println!();
+        println!("Stop Conditions (default: run until killed):");
+        println!("      --max-results <n>          Stop after n results");
+        println!("      --timeout <duration>       Stop after a duration, e.g. 90s, 15m, 2h or 1d");
+        println!("      --max-attempts <n>         Stop after checking n salts");
+        println!();
+        println!("Salt Layout (default: implementation address, then 12 searched bytes):");
+        println!(
+            "      --salt-prefix <hex>        Fixed leading salt bytes (up to 25), zero-padded"
+        );
+        println!("      --salt-template <hex>      64 hex digits with XX for 7-12 searched bytes");
+        println!();
+        println!("Resuming:");
+        println!("      --seed <n>                 Seed for the salt sequence (default: random)");
+        println!(
+            "      --checkpoint <path>        Record progress to a file, resuming if it exists"
+        );
+        println!();
+        println!(
+            "  Ctrl-C (SIGINT) or SIGTERM stops part-way through the current batch and prints a"
+        );
+        println!("  summary; a resumed --checkpoint run scans that batch again.");
+        println!("  Exit status: 0 = stop condition reached, 1 = error, 130 = interrupted.");
         println!();
         println!("Optional Arguments:");
         println!(
             "  -r, --registry <address>       ERC6551 Registry (default: 0x000000006551c19487814612e58FE06813775758)"
         );
+        println!("      --case-sensitive           Match letter case against the EIP-55 checksum");
+        println!(
+            "  -o, --output <path>            Result file (default: result.txt, .json, .jsonl or .csv)"
+        );
+        println!("      --no-file                  Only print results, don't write a file");
+        println!("      --format <format>          text, json, jsonl or csv (default: text)");
         println!("  -w, --workers <num>            Number of CPU threads (default: all cores)");
         println!("  -g, --gpu                      Use GPU acceleration (OpenCL)");
-        println!("      --list-gpus                List available GPU devices");
+        println!("      --hybrid                   Use the CPU and the GPU(s) at the same time");
+        println!(
+            "      --platform <index|name|all> OpenCL platform (default: the default platform)"
+        );
+        println!("      --device <index|name|all>  OpenCL device(s), comma-separated or repeated");
+        println!("                                 (default: the platform's first)");
+        println!("      --list-gpus                List OpenCL platforms and devices with indices");
+        println!("      --launch-size <num>        Salts per GPU kernel launch (default: tuned)");
+        println!("                                 (--batch-size is an alias)");
+        println!(
+            "      --local-size <num>         GPU work-group size, 0 for the driver's (default: tuned)"
+        );
+        println!(
+            "      --tune-cache <path>        Remember each GPU's tuned sizes, skipping tuning later"
+        );
+        println!(
+            "      --retune                   Tune again even if the tuning cache has the GPU"
+        );
         println!("  -h, --help                     Show this help message");
         println!();
         println!("Examples:");
@@ -36,13 +108,73 @@ fn main() {
         println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
         println!("                -p 00000");
         println!();
+        println!("  # Find addresses starting with 'dead' and ending with 'beef':");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                -p dead --suffix beef");
+        println!();
+        println!("  # Find addresses with zero nibbles at fixed positions, ending in 'dead':");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                --mask 00XX00XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXdead");
+        println!();
+        println!("  # Search several patterns in one pass:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                -p 0000 -p dead --contains cafe --patterns-file patterns.txt");
+        println!();
+        println!("  # Find gas-efficient addresses with at least 4 zero bytes:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                --total-zero-bytes 4");
+        println!();
         println!("  # GPU mode - Much faster:");
         println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
         println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
         println!("                -p 00000000 --gpu");
         println!();
+        println!("  # Stop after the first 5 results or one hour, whichever comes first:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                -p 00000000 --gpu --max-results 5 --timeout 1h");
+        println!();
+        println!("  # Keep the first 12 salt bytes zero and search the next 12:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                -p 0000 --salt-prefix 0x000000000000000000000000");
+        println!();
+        println!("  # The same vanity account on Ethereum, Base and Polygon:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!(
+            "                -c 1 -c 8453 -c 137 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D \\"
+        );
+        println!("                -t 1 -p 0000");
+        println!();
+        println!("  # One vanity account for each of the first 10000 tokens, resumable:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D \\");
+        println!("                --token-range 1..10000 -p 0000 --checkpoint collection.ckpt");
+        println!();
+        println!("  # Use the second device of the NVIDIA platform:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                -p 00000000 --gpu --platform nvidia --device 1");
+        println!();
+        println!("  # Use every device of every platform at once:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                -p 00000000 --gpu --platform all --device all");
+        println!();
+        println!("  # Keep the CPU busy alongside the GPU:");
+        println!("  erc6551crunch -i 0x55266d75D1a14E4572138116aF39863Ed6596E7F \\");
+        println!("                -c 1 -n 0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D -t 1 \\");
+        println!("                -p 00000000 --hybrid");
+        println!();
         println!("  # List available GPUs:");
         println!("  erc6551crunch --list-gpus");
+        println!();
+        println!("  # Verify a salt offline (see `erc6551crunch verify --help`):");
+        println!("  erc6551crunch verify --salt 0x... -i 0x... -c 1 -n 0x... -t 1 -e 0x...");
         process::exit(0);
     }
 
@@ -52,20 +184,86 @@ fn main() {
         process::exit(1);
     });
 
-    if config.use_gpu {
+    // The first Ctrl-C cuts the current batch short and prints the summary; a second one quits.
+    // Quitting mid-write loses nothing: text, JSONL and CSV results are appended a line at a
+    // time and a JSON file is only ever replaced whole, and the checkpoint lags behind
+    ctrlc::set_handler(|| {
+        if erc6551crunch::interrupt() {
+            process::exit(EXIT_INTERRUPTED);
+        }
+        eprintln!("\n🛑 Stopping the search (Ctrl-C again to quit immediately)...");
+    })
+    .unwrap_or_else(|e| eprintln!("Warning: could not install the Ctrl-C handler: {e}"));
+
+    let summary = if config.hybrid {
+        println!("🚀 Hybrid Mode enabled (CPU + GPU)");
+        erc6551crunch::hybrid(config).unwrap_or_else(|e| {
+            eprintln!("Hybrid application error: {e}");
+            print_opencl_tip();
+            process::exit(1);
+        })
+    } else if config.use_gpu {
         println!("🚀 GPU Mode enabled");
-        if let Err(e) = erc6551crunch::gpu(config) {
+        erc6551crunch::gpu(config).unwrap_or_else(|e| {
             eprintln!("GPU application error: {e}");
-            eprintln!("Tip: Make sure you have OpenCL drivers installed.");
-            eprintln!("     For NVIDIA: Install CUDA Toolkit");
-            eprintln!("     For AMD: Install AMD APP SDK or ROCm");
-            eprintln!("     For Intel: Install Intel OpenCL Runtime");
+            print_opencl_tip();
             process::exit(1);
-        }
+        })
     } else {
-        if let Err(e) = erc6551crunch::cpu(config) {
+        erc6551crunch::cpu(config).unwrap_or_else(|e| {
             eprintln!("CPU application error: {e}");
             process::exit(1);
+        })
+    };
+
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("{summary}");
+    if summary.stop_reason == StopReason::Interrupted {
+        process::exit(EXIT_INTERRUPTED);
+    }
+}
+
+fn print_opencl_tip() {
+    eprintln!("Tip: Make sure you have OpenCL drivers installed.");
+    eprintln!("     For NVIDIA: Install CUDA Toolkit");
+    eprintln!("     For AMD: Install AMD APP SDK or ROCm");
+    eprintln!("     For Intel: Install Intel OpenCL Runtime");
+}
+
+fn run_verify(args: &[String]) -> ! {
+    if args.len() < 3 || args.contains(&"--help".to_string()) || args.contains(&"-h".to_string()) {
+        println!("Usage: erc6551crunch verify [OPTIONS]");
+        println!();
+        println!("Recomputes a tokenbound account address from a salt, without network access.");
+        println!();
+        println!("Required Arguments:");
+        println!("  -s, --salt <hex>               32-byte salt from result.txt");
+        println!("  -i, --impl <address>           Implementation contract address");
+        println!("  -c, --chain <id>               Chain ID (uint256, decimal or 0x hex)");
+        println!("  -n, --nft <address>            NFT contract address");
+        println!("  -t, --token <id>               Token ID (uint256, decimal or 0x hex)");
+        println!();
+        println!("Optional Arguments:");
+        println!(
+            "  -r, --registry <address>       ERC6551 Registry (default: 0x000000006551c19487814612e58FE06813775758)"
+        );
+        println!("  -e, --expected <address>       Exit with status 1 if the address differs");
+        println!("  -h, --help                     Show this help message");
+        process::exit(0);
+    }
+
+    let config = VerifyConfig::new(env::args()).unwrap_or_else(|err| {
+        eprintln!("Error: {err}");
+        eprintln!("Use `erc6551crunch verify --help` for usage information.");
+        process::exit(1);
+    });
+
+    match erc6551crunch::verify(config) {
+        Ok(true) => process::exit(0),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("Verify error: {e}");
+            process::exit(1);
         }
     }
 }
@@ -0,0 +1,231 @@
+//! Keccak-256 inputs laid out as state lanes, with everything but the salt's counter absorbed
+//! ahead of time.
+//!
+//! Both hashes of a candidate are made of whole padded blocks whose only varying bytes are the
+//! salt's counter and, for CREATE2, the init code hash. They are packed once per batch, so
+//! hashing a salt is XORing those bytes into a copy of the lanes and permuting.
+
+use alloy_primitives::{Address, B256};
+use tiny_keccak::keccakf;
+
+use crate::CONTROL_CHARACTER;
+use crate::salt;
+
+/// Lanes in a Keccak-256 block, 136 bytes.
+pub(crate) const RATE_LANES: usize = 17;
+
+const RATE: usize = RATE_LANES * 8;
+const INIT_CODE_LEN: usize = 55 + 32 + 96;
+const CREATE2_LEN: usize = 1 + 20 + 32 + 32;
+/// Where the salt starts in the init code and in the CREATE2 input.
+const INIT_CODE_SALT: usize = 55;
+const CREATE2_SALT: usize = 21;
+/// Where the init code hash starts in the CREATE2 input, five bytes into lane 6.
+const CREATE2_HASH: usize = 53;
+
+/// The padded blocks of one batch's hashes, with the counter and init code hash left zero.
+pub(crate) struct Midstate {
+    /// `0xff`, registry and salt.
+    pub create2: [u64; RATE_LANES],
+    /// Both blocks of each target's init code: header, salt and footer.
+    pub init_code: Vec<[[u64; RATE_LANES]; 2]>,
+    counter_offset: usize,
+}
+
+impl Midstate {
+    /// Blocks for `salt_base`, with one init code per footer.
+    pub fn new(
+        registry: &[u8; 20],
+        bytecode_header: &[u8; 55],
+        bytecode_footers: &[[u8; 96]],
+        salt_base: &[u8; 32],
+        counter_offset: usize,
+    ) -> Self {
+        let mut create2 = [0u8; CREATE2_LEN];
+        create2[0] = CONTROL_CHARACTER;
+        create2[1..CREATE2_SALT].copy_from_slice(registry);
+        create2[CREATE2_SALT..CREATE2_HASH].copy_from_slice(salt_base);
+
+        let init_code = bytecode_footers
+            .iter()
+            .map(|footer| {
+                let mut init_code = [0u8; INIT_CODE_LEN];
+                init_code[..INIT_CODE_SALT].copy_from_slice(bytecode_header);
+                init_code[INIT_CODE_SALT..INIT_CODE_SALT + 32].copy_from_slice(salt_base);
+                init_code[INIT_CODE_SALT + 32..].copy_from_slice(footer);
+                [padded(&init_code[..RATE]), padded(&init_code[RATE..])]
+            })
+            .collect();
+
+        Self {
+            create2: padded(&create2),
+            init_code,
+            counter_offset,
+        }
+    }
+
+    /// The init code hash of the `target`th init code for `counter`, as state lanes.
+    pub fn init_code_hash(&self, target: usize, counter: u64) -> [u64; 4] {
+        let [first, second] = &self.init_code[target];
+        let mut state = [0u64; 25];
+        state[..RATE_LANES].copy_from_slice(first);
+        xor_counter(&mut state, INIT_CODE_SALT + self.counter_offset, counter);
+        keccakf(&mut state);
+        for (lane, block) in state.iter_mut().zip(second) {
+            *lane ^= block;
+        }
+        keccakf(&mut state);
+        [state[0], state[1], state[2], state[3]]
+    }
+
+    /// The CREATE2 address for `counter` and its init code hash, as lanes 1 to 3 of the state;
+    /// the address is their last 20 bytes.
+    pub fn address(&self, counter: u64, init_code_hash: &[u64; 4]) -> [u64; 3] {
+        let mut state = [0u64; 25];
+        state[..RATE_LANES].copy_from_slice(&self.create2);
+        xor_counter(&mut state, CREATE2_SALT + self.counter_offset, counter);
+        for (i, lane) in init_code_hash.iter().enumerate() {
+            state[CREATE2_HASH / 8 + i] ^= lane << 40;
+            state[CREATE2_HASH / 8 + i + 1] ^= lane >> 24;
+        }
+        keccakf(&mut state);
+        [state[1], state[2], state[3]]
+    }
+}
+
+/// XORs a counter into the state at byte `position`, where it may straddle two lanes.
+fn xor_counter(state: &mut [u64; 25], position: usize, counter: u64) {
+    let (lane, shift) = (position / 8, position % 8 * 8);
+    state[lane] ^= counter << shift;
+    if shift + salt::COUNTER_LEN * 8 > 64 {
+        state[lane + 1] ^= counter >> (64 - shift);
+    }
+}
+
+/// The final block of a message ending in `bytes`, padded and read as little-endian lanes.
+fn padded(bytes: &[u8]) -> [u64; RATE_LANES] {
+    let mut block = [0u8; RATE];
+    block[..bytes.len()].copy_from_slice(bytes);
+    if bytes.len() < RATE {
+        block[bytes.len()] ^= 0x01;
+        block[RATE - 1] ^= 0x80;
+    }
+    let mut lanes = [0u64; RATE_LANES];
+    for (lane, chunk) in lanes.iter_mut().zip(block.chunks_exact(8)) {
+        *lane = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    lanes
+}
+
+/// Hash lanes as the bytes they stand for.
+pub(crate) fn hash_bytes(lanes: &[u64; 4]) -> B256 {
+    let mut bytes = [0u8; 32];
+    for (chunk, lane) in bytes.chunks_exact_mut(8).zip(lanes) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    bytes.into()
+}
+
+/// The address held in lanes 1 to 3 of a Keccak-256 state.
+pub(crate) fn address_bytes(lanes: &[u64; 3]) -> Address {
+    let mut bytes = [0u8; 24];
+    for (chunk, lane) in bytes.chunks_exact_mut(8).zip(lanes) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    Address::from_slice(&bytes[4..])
+}
+
+/// 20 address bytes packed like [`Midstate::address`]'s lanes, for matching against them.
+pub(crate) fn address_lanes(bytes: &[u8; 20]) -> [u64; 3] {
+    let mut padded = [0u8; 24];
+    padded[4..].copy_from_slice(bytes);
+    let mut lanes = [0u64; 3];
+    for (lane, chunk) in lanes.iter_mut().zip(padded.chunks_exact(8)) {
+        *lane = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    lanes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tba::{TbaParams, compute_tba_address, tba_init_code_hash};
+    use crate::{ERC6551_REGISTRY, MAX_INCREMENTER, Target, bytecode_footer, bytecode_header};
+    use alloy_primitives::U256;
+
+    const IMPLEMENTATION: [u8; 20] = [0x55; 20];
+
+    fn targets() -> Vec<Target> {
+        let target = |chain_id: u64, nft_address: u8, token_id: u64| Target {
+            chain_id: U256::from(chain_id).to_be_bytes(),
+            nft_address: [nft_address; 20],
+            token_id: U256::from(token_id).to_be_bytes(),
+        };
+        vec![
+            target(1, 0xbc, 1),
+            target(8453, 0xbc, 1),
+            target(1, 0x07, 0),
+            target(137, 0xff, u64::MAX),
+        ]
+    }
+
+    fn params(target: &Target) -> TbaParams {
+        TbaParams {
+            implementation: IMPLEMENTATION.into(),
+            chain_id: U256::from_be_bytes(target.chain_id),
+            token_contract: target.nft_address.into(),
+            token_id: U256::from_be_bytes(target.token_id),
+        }
+    }
+
+    #[test]
+    fn lanes_hash_like_the_plain_derivation() {
+        let targets = targets();
+        let footers: Vec<_> = targets.iter().map(bytecode_footer).collect();
+        let counters = [0, 1, 0x1234_5678_9abc, MAX_INCREMENTER - 1, MAX_INCREMENTER];
+
+        // The default layout puts the counter at 26; 0 and 20 straddle lanes differently
+        for counter_offset in [0, 3, 13, 20, 26] {
+            let mut salt_base = [0u8; 32];
+            for (i, byte) in salt_base.iter_mut().enumerate() {
+                *byte = 0xa0 ^ i as u8;
+            }
+            salt_base[counter_offset..counter_offset + salt::COUNTER_LEN].fill(0);
+            let midstate = Midstate::new(
+                &ERC6551_REGISTRY,
+                &bytecode_header(&IMPLEMENTATION),
+                &footers,
+                &salt_base,
+                counter_offset,
+            );
+
+            for counter in counters {
+                let mut salt = salt_base;
+                salt[counter_offset..counter_offset + salt::COUNTER_LEN]
+                    .copy_from_slice(&counter.to_le_bytes()[..salt::COUNTER_LEN]);
+                let salt = B256::from(salt);
+
+                for (i, target) in targets.iter().enumerate() {
+                    let hash = midstate.init_code_hash(i, counter);
+                    assert_eq!(
+                        hash_bytes(&hash),
+                        tba_init_code_hash(&params(target), salt),
+                        "init code hash, counter offset {counter_offset}, counter {counter:#x}, target {i}"
+                    );
+                    assert_eq!(
+                        address_bytes(&midstate.address(counter, &hash)),
+                        compute_tba_address(ERC6551_REGISTRY.into(), &params(target), salt),
+                        "address, counter offset {counter_offset}, counter {counter:#x}, target {i}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn address_lanes_pack_like_address_bytes() {
+        let address: [u8; 20] = std::array::from_fn(|i| 0x0b * i as u8);
+        let lanes = address_lanes(&address);
+        assert_eq!(address_bytes(&lanes), Address::from(address));
+    }
+}
@@ -0,0 +1,262 @@
+//! Running several backends at once, each on its own share of every batch.
+
+use std::error::Error;
+use std::ops::Range;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use crate::cpu::CpuBackend;
+use crate::gpu::opencl_backends;
+use crate::progress::{Meter, RunSummary};
+use crate::search::{Batch, BatchOutcome, SearchBackend, search};
+use crate::{Config, Target};
+
+/// Searches on the CPU and the OpenCL devices selected by `--platform` and `--device` at once.
+///
+/// Without `--workers`, one core is left free for each device to feed it.
+pub fn hybrid(config: Config) -> Result<RunSummary, Box<dyn Error>> {
+    let gpus = opencl_backends(&config)?;
+    let num_threads = if config.num_threads > 0 {
+        config.num_threads
+    } else {
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+        cores.saturating_sub(gpus.len()).max(1)
+    };
+
+    let mut backends: Vec<Box<dyn SearchBackend + Send>> =
+        vec![Box::new(CpuBackend::new(num_threads))];
+    backends.extend(
+        gpus.into_iter()
+            .map(|backend| Box::new(backend) as Box<dyn SearchBackend + Send>),
+    );
+    search(&config, &mut MultiBackend::new(backends))
+}
+
+/// Splits every batch into disjoint counter ranges, one per backend, and hashes them in parallel.
+///
+/// Shares are sized by how fast each backend got through its last one, so a batch takes about
+/// as long on every backend. Until a backend has been measured its batch size stands in for
+/// its speed.
+pub struct MultiBackend {
+    backends: Vec<Box<dyn SearchBackend + Send>>,
+    rates: Vec<f64>,
+    meters: Vec<Meter>,
+}
+
+impl MultiBackend {
+    pub fn new(backends: Vec<Box<dyn SearchBackend + Send>>) -> Self {
+        // Backends are numbered when there are several of a kind, e.g. `GPU 0` and `GPU 1`
+        let names: Vec<&str> = backends.iter().map(|backend| backend.name()).collect();
+        let meters = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let kind = name.to_uppercase();
+                if names.iter().filter(|other| *other == name).count() > 1 {
+                    let index = names[..i].iter().filter(|other| *other == name).count();
+                    Meter::new(format!("{} {}", kind, index))
+                } else {
+                    Meter::new(kind)
+                }
+            })
+            .collect();
+        Self {
+            rates: Vec::new(), // set by `prepare`
+            backends,
+            meters,
+        }
+    }
+
+    /// `counters` cut into one contiguous range per backend, in proportion to their rates.
+    ///
+    /// The counters are split evenly while no backend has a rate, e.g. before `prepare`.
+    fn shares(&self, counters: &Range<u64>) -> Vec<Range<u64>> {
+        let count = self.backends.len();
+        let total: f64 = self.rates.iter().sum();
+        let measured = self.rates.len() == count && total > 0.0;
+        let rate = |i: usize| if measured { self.rates[i] } else { 1.0 };
+        let total = if measured { total } else { count as f64 };
+
+        // Each share ends where the rates so far put it, so rounding never piles up on one backend
+        let len = (counters.end - counters.start) as f64;
+        let mut start = counters.start;
+        let mut cumulative = 0.0;
+        let mut shares = Vec::with_capacity(count);
+        for i in 0..count {
+            cumulative += rate(i);
+            let end = if i + 1 == count {
+                counters.end
+            } else {
+                (counters.start + (len * cumulative / total) as u64).clamp(start, counters.end)
+            };
+            shares.push(start..end);
+            start = end;
+        }
+        shares
+    }
+}
+
+impl SearchBackend for MultiBackend {
+    fn name(&self) -> &'static str {
+        let name = self.backends[0].name();
+        if self.backends.iter().all(|backend| backend.name() == name) {
+            name
+        } else {
+            "hybrid"
+        }
+    }
+
+    fn speed_label(&self) -> &'static str {
+        let label = self.backends[0].speed_label();
+        if self
+            .backends
+            .iter()
+            .all(|backend| backend.speed_label() == label)
+        {
+            label
+        } else {
+            "⚡ Speed"
+        }
+    }
+
+    fn batch_size(&self) -> u64 {
+        self.backends
+            .iter()
+            .map(|backend| backend.batch_size())
+            .sum()
+    }
+
+    fn prepare(&mut self, config: &Config, targets: &[Target]) -> Result<(), Box<dyn Error>> {
+        for backend in &mut self.backends {
+            backend.prepare(config, targets)?;
+        }
+        // Batch sizes are only final once the backends are prepared, e.g. a tuned GPU's
+        self.rates = self
+            .backends
+            .iter()
+            .map(|backend| backend.batch_size() as f64)
+            .collect();
+        Ok(())
+    }
+
+    fn describe(&self) -> Vec<String> {
+        self.backends
+            .iter()
+            .flat_map(|backend| backend.describe())
+            .collect()
+    }
+
+    fn meters(&self) -> Vec<Meter> {
+        self.meters.clone()
+    }
+
+    fn run_batch(
+        &mut self,
+        config: &Config,
+        batch: &Batch,
+        stop: &(dyn Fn() -> bool + Sync),
+    ) -> Result<BatchOutcome, Box<dyn Error>> {
+        let shares = self.shares(&batch.counters);
+
+        // Errors are carried out of the worker threads as strings
+        let results: Vec<Result<(BatchOutcome, f64), String>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = self
+                .backends
+                .iter_mut()
+                .zip(shares)
+                .map(|(backend, counters)| {
+                    let share = Batch {
+                        counters,
+                        ..batch.clone()
+                    };
+                    scope.spawn(move || {
+                        if share.counters.is_empty() {
+                            return Ok((BatchOutcome::default(), 0.0));
+                        }
+                        let start = Instant::now();
+                        let outcome = backend
+                            .run_batch(config, &share, stop)
+                            .map_err(|err| err.to_string())?;
+                        Ok((outcome, start.elapsed().as_secs_f64()))
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("backend thread panicked"))
+                .collect()
+        });
+
+        let mut merged = BatchOutcome::default();
+        for (i, result) in results.into_iter().enumerate() {
+            let (outcome, elapsed) = result?;
+            self.meters[i]
+                .checked
+                .fetch_add(outcome.checked, Ordering::Relaxed);
+            if outcome.checked > 0 && elapsed > 0.0 {
+                self.rates[i] = outcome.checked as f64 / elapsed;
+            }
+            merged.checked += outcome.checked;
+            merged.overflowed += outcome.overflowed;
+            merged.candidates.extend(outcome.candidates);
+        }
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CPU backends sharing batches by `rates`, which are left empty if `None`.
+    fn multi(count: usize, rates: Option<&[f64]>) -> MultiBackend {
+        let backends = (0..count)
+            .map(|_| Box::new(CpuBackend::new(1)) as Box<dyn SearchBackend + Send>)
+            .collect();
+        let mut multi = MultiBackend::new(backends);
+        if let Some(rates) = rates {
+            multi.rates = rates.to_vec();
+        }
+        multi
+    }
+
+    /// Checks that `shares` has one range per backend and cuts `counters` into contiguous
+    /// pieces, returning their lengths.
+    fn share_lens(multi: &MultiBackend, counters: Range<u64>) -> Vec<u64> {
+        let shares = multi.shares(&counters);
+        assert_eq!(shares.len(), multi.backends.len());
+        assert_eq!(shares[0].start, counters.start);
+        assert_eq!(shares.last().unwrap().end, counters.end);
+        for pair in shares.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start, "{shares:?}");
+        }
+        shares.iter().map(|share| share.end - share.start).collect()
+    }
+
+    #[test]
+    fn shares_follow_the_rates() {
+        let multi = multi(3, Some(&[1.0, 3.0, 4.0]));
+        assert_eq!(share_lens(&multi, 1000..1800), [100, 300, 400]);
+        assert_eq!(share_lens(&multi, 0..10), [1, 4, 5]);
+    }
+
+    #[test]
+    fn shares_are_even_without_rates() {
+        assert_eq!(share_lens(&multi(2, None), 5..105), [50, 50]);
+        assert_eq!(share_lens(&multi(3, Some(&[0.0; 3])), 0..9), [3, 3, 3]);
+    }
+
+    #[test]
+    fn a_backend_with_no_rate_gets_nothing() {
+        let multi = multi(3, Some(&[2.0, 0.0, 2.0]));
+        assert_eq!(share_lens(&multi, 0..100), [50, 0, 50]);
+    }
+
+    #[test]
+    fn batches_smaller_than_the_backend_count_are_still_covered() {
+        let multi = multi(4, Some(&[1.0; 4]));
+        assert_eq!(share_lens(&multi, 7..8), [0, 0, 0, 1]);
+        assert_eq!(share_lens(&multi, 7..10), [0, 1, 1, 1]);
+        assert_eq!(share_lens(&multi, 7..7), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,403 @@
+//! Where found salts are written, and in which format.
+
+use alloy_primitives::{Address, B256, U256};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::prelude::*;
+use std::io::{ErrorKind, stdout};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Config, Pattern, Target, write_atomically};
+
+const CSV_HEADER: &str = "salt,address,init_code_hash,registry,implementation,chain_id,nft,token_id,pattern,score,backend,timestamp";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text, // `salt => address`, as printed to the terminal
+    Json,  // A single JSON array, rewritten as results come in
+    Jsonl, // One JSON object per line
+    Csv,   // Comma-separated with a header row
+}
+
+impl FromStr for OutputFormat {
+    type Err = &'static str;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            _ => Err("--format must be text, json, jsonl or csv"),
+        }
+    }
+}
+
+/// A found salt and the address it produces.
+pub(crate) struct Found<'a> {
+    pub salt: B256,
+    pub address: Address,
+    pub init_code_hash: B256,
+    pub target: &'a Target,
+    pub pattern: &'a Pattern,
+    pub score: u32,
+}
+
+/// Prints results to stdout and appends them to the output file, shared by all workers.
+pub(crate) struct ResultSink {
+    format: OutputFormat,
+    backend: &'static str,
+    registry: Address,
+    implementation: Address,
+    tagged: bool,
+    // Target fields that differ between targets, and so are appended to text results
+    tag_chain: bool,
+    tag_nft: bool,
+    tag_token: bool,
+    file: Mutex<Option<OutputFile>>,
+}
+
+enum OutputFile {
+    /// Text, JSONL and CSV files, appended to a line per result.
+    Lines { file: File, path: PathBuf },
+    /// A JSON array, kept in memory and replaced atomically with each result.
+    Json { path: PathBuf, records: Vec<String> },
+}
+
+impl ResultSink {
+    pub fn new(config: &Config, backend: &'static str) -> Result<Self, Box<dyn Error>> {
+        let file = if config.no_file {
+            println!("📁 Not saving results to a file (--no-file)");
+            None
+        } else {
+            let path = config
+                .output
+                .clone()
+                .unwrap_or_else(|| default_output_path(config.format));
+            println!("📁 Saving results to: {}", path.display());
+            Some(OutputFile::open(path, config.format)?)
+        };
+        // A collection's tokens share the one target's chain and NFT
+        let targets = &config.targets;
+        Ok(Self {
+            format: config.format,
+            backend,
+            registry: Address::from(config.resistry_address),
+            implementation: Address::from(config.implement_address),
+            tagged: config.patterns.len() > 1,
+            tag_chain: targets.iter().any(|t| t.chain_id != targets[0].chain_id),
+            tag_nft: targets
+                .iter()
+                .any(|t| t.nft_address != targets[0].nft_address),
+            tag_token: config.collection.len() > 1
+                || targets.iter().any(|t| t.token_id != targets[0].token_id),
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, found: &Found) {
+        print!("\n{}\n", self.text_line(found));
+        let _ = stdout().flush();
+
+        let mut file = self.file.lock().unwrap();
+        let Some(output) = file.as_mut() else {
+            return;
+        };
+        let record = match self.format {
+            OutputFormat::Text => self.text_line(found),
+            OutputFormat::Json | OutputFormat::Jsonl => self.json_object(found),
+            OutputFormat::Csv => self.csv_row(found),
+        };
+        if let Err(err) = output.write(record) {
+            panic!("Couldn't write to `{}`: {}", output.path().display(), err);
+        }
+    }
+
+    /// Flushes the output file to disk at the end of a run.
+    pub fn finish(&self) -> std::io::Result<()> {
+        match self.file.lock().unwrap().as_mut() {
+            Some(OutputFile::Lines { file, .. }) => file.sync_all(),
+            // Synced with every result
+            Some(OutputFile::Json { .. }) | None => Ok(()),
+        }
+    }
+
+    /// Formats a result as `salt => address`, followed by the score in score-based modes.
+    ///
+    /// When several patterns are searched at once, the matched pattern is appended as a tag, and
+    /// when several targets are, the chain, NFT or token that tells them apart is too.
+    fn text_line(&self, found: &Found) -> String {
+        // `Address` displays in EIP-55 checksummed form
+        let mut line = format!("{} => {}", found.salt, found.address);
+        if found.pattern.mode.is_scored() {
+            line += &format!(" (score: {})", found.score);
+        }
+        if self.tagged {
+            line += &format!(" [{}]", found.pattern);
+        }
+        let target = found.target;
+        let mut target_tags = Vec::new();
+        if self.tag_chain {
+            target_tags.push(format!("chain {}", U256::from_be_bytes(target.chain_id)));
+        }
+        if self.tag_nft {
+            target_tags.push(format!("nft {}", Address::from(target.nft_address)));
+        }
+        if self.tag_token {
+            target_tags.push(format!("token {}", U256::from_be_bytes(target.token_id)));
+        }
+        if !target_tags.is_empty() {
+            line += &format!(" [{}]", target_tags.join(", "));
+        }
+        line
+    }
+
+    // Every field is hex, decimal or a validated pattern tag, so nothing needs escaping
+    fn json_object(&self, found: &Found) -> String {
+        format!(
+            concat!(
+                r#"{{"salt":"{}","address":"{}","init_code_hash":"{}","registry":"{}","#,
+                r#""implementation":"{}","chain_id":"{}","nft":"{}","token_id":"{}","#,
+                r#""pattern":"{}","score":{},"backend":"{}","timestamp":{}}}"#
+            ),
+            found.salt,
+            found.address,
+            found.init_code_hash,
+            self.registry,
+            self.implementation,
+            U256::from_be_bytes(found.target.chain_id),
+            Address::from(found.target.nft_address),
+            U256::from_be_bytes(found.target.token_id),
+            found.pattern,
+            found.score,
+            self.backend,
+            timestamp(),
+        )
+    }
+
+    fn csv_row(&self, found: &Found) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            found.salt,
+            found.address,
+            found.init_code_hash,
+            self.registry,
+            self.implementation,
+            U256::from_be_bytes(found.target.chain_id),
+            Address::from(found.target.nft_address),
+            U256::from_be_bytes(found.target.token_id),
+            found.pattern,
+            found.score,
+            self.backend,
+            timestamp(),
+        )
+    }
+}
+
+impl OutputFile {
+    /// Every format adds to what the file already holds: text, JSONL and CSV files are
+    /// appended to, and a JSON file's array is read back and extended. A file that isn't in
+    /// the format, such as a CSV file with another header, is refused rather than mixed into.
+    fn open(path: PathBuf, format: OutputFormat) -> Result<Self, Box<dyn Error>> {
+        if format == OutputFormat::Json {
+            let existing = match std::fs::read_to_string(&path) {
+                Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+                read => {
+                    read.map_err(|err| format!("Could not read `{}`: {}", path.display(), err))?
+                }
+            };
+            let records = parse_json_records(&existing).ok_or_else(|| {
+                format!(
+                    "`{}` is not a JSON array of results; choose another --output",
+                    path.display()
+                )
+            })?;
+            let output = Self::Json { path, records };
+            output
+                .write_json()
+                .map_err(|err| format!("Could not write `{}`: {}", output.path().display(), err))?;
+            return Ok(output);
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map_err(|err| format!("Could not open `{}`: {}", path.display(), err))?;
+        let mut existing = String::new();
+        file.read_to_string(&mut existing)
+            .map_err(|err| format!("Could not read `{}`: {}", path.display(), err))?;
+        match format {
+            OutputFormat::Csv if existing.is_empty() => writeln!(file, "{CSV_HEADER}")?,
+            OutputFormat::Csv if existing.lines().next() != Some(CSV_HEADER) => {
+                return Err(format!(
+                    "`{}` is not a CSV file of results; choose another --output",
+                    path.display()
+                )
+                .into());
+            }
+            _ => {}
+        }
+        Ok(Self::Lines { file, path })
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Self::Lines { path, .. } | Self::Json { path, .. } => path,
+        }
+    }
+
+    /// Adds one result's line or JSON record.
+    fn write(&mut self, record: String) -> std::io::Result<()> {
+        match self {
+            Self::Lines { file, .. } => writeln!(file, "{record}"),
+            Self::Json { records, .. } => {
+                records.push(record);
+                self.write_json()
+            }
+        }
+    }
+
+    /// Replaces the JSON file with the array of every record, so that it is a valid document
+    /// holding every earlier result even if the run is killed while writing.
+    fn write_json(&self) -> std::io::Result<()> {
+        let Self::Json { path, records } = self else {
+            return Ok(());
+        };
+        let mut json = String::from("[");
+        for (i, record) in records.iter().enumerate() {
+            json += if i == 0 { "\n  " } else { ",\n  " };
+            json += record;
+        }
+        json += if records.is_empty() { "]\n" } else { "\n]\n" };
+        write_atomically(path, &json)
+    }
+}
+
+/// The records of a JSON array as [`OutputFile::write_json`] writes them, one per line, or
+/// `None` if `json` is something else. An empty file has none.
+fn parse_json_records(json: &str) -> Option<Vec<String>> {
+    let json = json.trim();
+    if json.is_empty() {
+        return Some(Vec::new());
+    }
+    let inner = json.strip_prefix('[')?.strip_suffix(']')?;
+    inner
+        .lines()
+        .map(|line| line.trim().trim_end_matches(','))
+        .filter(|record| !record.is_empty())
+        .map(|record| {
+            (record.starts_with('{') && record.ends_with('}')).then(|| record.to_string())
+        })
+        .collect()
+}
+
+/// `result.txt` (`.json`, `.jsonl` or `.csv` for the other formats) in the project root when
+/// running from `target/release`, otherwise next to the executable.
+fn default_output_path(format: OutputFormat) -> PathBuf {
+    let file_name = match format {
+        OutputFormat::Text => "result.txt",
+        OutputFormat::Json => "result.json",
+        OutputFormat::Jsonl => "result.jsonl",
+        OutputFormat::Csv => "result.csv",
+    };
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .and_then(|dir| {
+            // If running from target/release, go up 2 levels to project root
+            if dir.ends_with("release") || dir.ends_with("debug") {
+                dir.parent()
+                    .and_then(|p| p.parent())
+                    .map(|p| p.to_path_buf())
+            } else {
+                Some(dir)
+            }
+        })
+        .map(|dir| dir.join(file_name))
+        .unwrap_or_else(|| PathBuf::from(file_name))
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_json_records_reads_back_written_arrays() {
+        assert_eq!(parse_json_records(""), Some(Vec::new()));
+        assert_eq!(parse_json_records("[]\n"), Some(Vec::new()));
+        assert_eq!(
+            parse_json_records("[\n  {\"a\":1,\"b\":2},\n  {\"a\":3}\n]\n"),
+            Some(vec![
+                r#"{"a":1,"b":2}"#.to_string(),
+                r#"{"a":3}"#.to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn json_results_extend_an_existing_array() {
+        let path =
+            std::env::temp_dir().join(format!("erc6551crunch-extend-{}.json", std::process::id()));
+        std::fs::write(&path, "[\n  {\"salt\":\"0x01\"}\n]\n").unwrap();
+        let args = [
+            "erc6551crunch",
+            "-i",
+            "0x55266d75D1a14E4572138116aF39863Ed6596E7F",
+            "-c",
+            "1",
+            "-n",
+            "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D",
+            "-t",
+            "1",
+            "-p",
+            "dead",
+            "--format",
+            "json",
+            "--output",
+            path.to_str().unwrap(),
+        ];
+        let config = Config::new(args.into_iter().map(String::from)).unwrap();
+
+        let sink = ResultSink::new(&config, "cpu").unwrap();
+        let found = Found {
+            salt: B256::repeat_byte(0x02),
+            address: Address::repeat_byte(0xde),
+            init_code_hash: B256::repeat_byte(0x03),
+            target: &config.targets[0],
+            pattern: &config.patterns[0],
+            score: 0,
+        };
+        sink.record(&found);
+        sink.record(&found);
+        sink.finish().unwrap();
+
+        let records = parse_json_records(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], r#"{"salt":"0x01"}"#);
+        assert!(records[2].contains(&format!(r#""salt":"{}""#, found.salt)));
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        assert!(!Path::new(&temp_path).exists());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn parse_json_records_rejects_other_files() {
+        assert_eq!(parse_json_records("0x12 => 0x34\n"), None);
+        assert_eq!(parse_json_records("{\"a\":1}\n"), None);
+        assert_eq!(parse_json_records("[\n  1,\n  2\n]\n"), None);
+    }
+}
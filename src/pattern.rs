@@ -0,0 +1,577 @@
+use alloy_primitives::Address;
+use std::fmt;
+
+use crate::PatternMode;
+
+/// A single search target, validated and compiled once so candidates can be checked
+/// against many of them per hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub mode: PatternMode,
+    /// Hex pattern without `0x`, in the letter case the user typed it.
+    pub pattern: String,
+    /// Suffix of a [`PatternMode::PrefixSuffix`] pattern, empty otherwise.
+    pub suffix: String,
+    /// Minimum score in score-based modes.
+    pub min_score: u32,
+    address_mask: AddressMask,
+    nibbles: Vec<u8>,
+}
+
+impl Pattern {
+    pub fn new(
+        mode: PatternMode,
+        pattern: &str,
+        suffix: &str,
+        min_score: u32,
+    ) -> Result<Self, &'static str> {
+        // Drop any `0x`, keeping a mask's leading nibbles when it's exactly 40 long.
+        // Letter case is kept for --case-sensitive; matching itself is case-insensitive.
+        let pattern = match mode {
+            PatternMode::Mask if pattern.len() == 40 => pattern,
+            _ => strip_hex_prefix(pattern),
+        };
+        let suffix = strip_hex_prefix(suffix);
+        if !mode.is_scored() && pattern.is_empty()
+            || mode == PatternMode::PrefixSuffix && suffix.is_empty()
+        {
+            return Err("pattern must not be empty");
+        }
+        if matches!(
+            mode,
+            PatternMode::LeadingZeroBytes | PatternMode::TotalZeroBytes
+        ) && !(1..=20).contains(&min_score)
+        {
+            return Err("zero-byte count must be a number from 1 to 20");
+        }
+        let (address_mask, nibbles) = match mode {
+            PatternMode::Contains => (AddressMask::default(), parse_nibbles(pattern)?),
+            _ if mode.uses_mask() => (
+                AddressMask::from_pattern(mode, pattern, suffix)?,
+                Vec::new(),
+            ),
+            _ => (AddressMask::default(), Vec::new()),
+        };
+        Ok(Self {
+            mode,
+            pattern: pattern.to_string(),
+            suffix: suffix.to_string(),
+            min_score,
+            address_mask,
+            nibbles,
+        })
+    }
+
+    /// Parses one line of a `--patterns-file`, or `None` for blank and `#` comment lines.
+    ///
+    /// Accepts a bare hex prefix, `prefix <hex>`, `suffix <hex>`, `prefix <hex> suffix <hex>`,
+    /// `contains <hex>`, `mask <nibbles>`, `leading-zero-bytes <n>` or `total-zero-bytes <n>`.
+    pub fn parse_line(line: &str) -> Option<Result<Self, &'static str>> {
+        let line = line.split('#').next().unwrap_or_default();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let zero_bytes = |count: &str| {
+            count
+                .parse()
+                .map_err(|_| "zero-byte count must be a number from 1 to 20")
+        };
+        let pattern = match words.as_slice() {
+            [] => return None,
+            [prefix] | ["prefix", prefix] => Self::new(PatternMode::Prefix, prefix, "", 0),
+            ["suffix", suffix] => Self::new(PatternMode::Suffix, suffix, "", 0),
+            ["prefix", prefix, "suffix", suffix] => {
+                Self::new(PatternMode::PrefixSuffix, prefix, suffix, 0)
+            }
+            ["contains", pattern] => Self::new(PatternMode::Contains, pattern, "", 0),
+            ["mask", mask] => Self::new(PatternMode::Mask, mask, "", 0),
+            ["leading-zero-bytes", count] => zero_bytes(count)
+                .and_then(|count| Self::new(PatternMode::LeadingZeroBytes, "", "", count)),
+            ["total-zero-bytes", count] => zero_bytes(count)
+                .and_then(|count| Self::new(PatternMode::TotalZeroBytes, "", "", count)),
+            _ => Err("invalid line in --patterns-file"),
+        };
+        Some(pattern)
+    }
+
+    /// Nibble mask for the mask-based modes, all wildcards otherwise.
+    pub fn address_mask(&self) -> &AddressMask {
+        &self.address_mask
+    }
+
+    /// Checks an address case-insensitively, returning its score if it matches.
+    #[inline]
+    pub fn matches(&self, address: &[u8]) -> Option<u32> {
+        let score = address_score(self.mode, &self.address_mask, address);
+        let matched = match self.mode {
+            PatternMode::Contains => contains_nibbles(address, &self.nibbles),
+            mode if mode.is_scored() => score >= self.min_score,
+            _ => self.address_mask.matches(address),
+        };
+        matched.then_some(score)
+    }
+
+    /// [`checksum_score`] for this pattern, rejecting scores that fall below `min_score`.
+    pub fn checksum_score(&self, address: &Address, score: u32) -> Option<u32> {
+        checksum_score(self.mode, &self.pattern, &self.suffix, address, score)
+            .filter(|score| *score >= self.min_score)
+    }
+}
+
+/// Short tag used to label results, e.g. `prefix 0xdead` or `leading-zero-bytes 4`.
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            PatternMode::Prefix => write!(f, "prefix 0x{}", self.pattern),
+            PatternMode::Contains => write!(f, "contains {}", self.pattern),
+            PatternMode::Suffix => write!(f, "suffix {}", self.pattern),
+            PatternMode::PrefixSuffix => {
+                write!(f, "prefix 0x{} suffix {}", self.pattern, self.suffix)
+            }
+            PatternMode::Mask => write!(f, "mask 0x{}", self.pattern),
+            PatternMode::LeadingZeroBytes => write!(f, "leading-zero-bytes {}", self.min_score),
+            PatternMode::TotalZeroBytes => write!(f, "total-zero-bytes {}", self.min_score),
+            PatternMode::PatternLength => write!(f, "pattern-length 0x{}", self.pattern),
+        }
+    }
+}
+
+/// Nibble-level address constraint: an address matches when `address & mask == value`.
+///
+/// Prefix, suffix and wildcard patterns all compile down to one of these, so
+/// candidates can be checked on raw address bytes without hex-encoding them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AddressMask {
+    pub value: [u8; 20],
+    pub mask: [u8; 20],
+}
+
+impl AddressMask {
+    /// Builds the mask for any pattern mode except [`PatternMode::Contains`].
+    pub fn from_pattern(
+        pattern_mode: PatternMode,
+        pattern: &str,
+        suffix: &str,
+    ) -> Result<Self, &'static str> {
+        match pattern_mode {
+            PatternMode::Prefix | PatternMode::PatternLength => Self::prefix(pattern),
+            PatternMode::Suffix => Self::suffix(pattern),
+            PatternMode::PrefixSuffix => {
+                let prefix = Self::prefix(pattern)?;
+                let suffix = Self::suffix(suffix)?;
+                prefix
+                    .and(&suffix)
+                    .ok_or("--prefix and --suffix overlap with different nibbles")
+            }
+            PatternMode::Mask => Self::parse(pattern),
+            PatternMode::Contains => Err("contains patterns cannot be expressed as a mask"),
+            PatternMode::LeadingZeroBytes | PatternMode::TotalZeroBytes => {
+                Err("zero-byte scores cannot be expressed as a mask")
+            }
+        }
+    }
+
+    /// Parses a 40-nibble mask such as `00XX00XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXdead`,
+    /// where `X`, `x` or `?` match any nibble.
+    pub fn parse(pattern: &str) -> Result<Self, &'static str> {
+        let pattern = if pattern.len() == 42 {
+            strip_hex_prefix(pattern)
+        } else {
+            pattern
+        };
+        if pattern.len() != 40 {
+            return Err("--mask must be exactly 40 nibbles long");
+        }
+        let mut result = Self::default();
+        for (position, c) in pattern.chars().enumerate() {
+            if matches!(c, 'X' | 'x' | '?') {
+                continue;
+            }
+            let nibble = c
+                .to_digit(16)
+                .ok_or("--mask must contain hex digits or X wildcards")?;
+            result.set_nibble(position, nibble as u8);
+        }
+        Ok(result)
+    }
+
+    /// Mask matching addresses that start with the hex `pattern`.
+    pub fn prefix(pattern: &str) -> Result<Self, &'static str> {
+        let nibbles = parse_nibbles(pattern)?;
+        let mut result = Self::default();
+        for (position, nibble) in nibbles.into_iter().enumerate() {
+            result.set_nibble(position, nibble);
+        }
+        Ok(result)
+    }
+
+    /// Mask matching addresses that end with the hex `pattern`.
+    pub fn suffix(pattern: &str) -> Result<Self, &'static str> {
+        let nibbles = parse_nibbles(pattern)?;
+        let offset = 40 - nibbles.len();
+        let mut result = Self::default();
+        for (position, nibble) in nibbles.into_iter().enumerate() {
+            result.set_nibble(offset + position, nibble);
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    pub fn matches(&self, address: &[u8]) -> bool {
+        address
+            .iter()
+            .zip(self.mask.iter().zip(&self.value))
+            .all(|(byte, (mask, value))| byte & mask == *value)
+    }
+
+    /// Number of leading constrained nibbles the address matches before the first mismatch.
+    pub fn prefix_score(&self, address: &[u8]) -> u32 {
+        let mut score = 0;
+        for position in 0..40usize {
+            let shift = if position.is_multiple_of(2) { 4 } else { 0 };
+            let mask = self.mask[position / 2] & (0x0f << shift);
+            if mask == 0 || address[position / 2] & mask != self.value[position / 2] & mask {
+                break;
+            }
+            score += 1;
+        }
+        score
+    }
+
+    /// Combines two masks, or returns `None` if they require different nibbles.
+    fn and(&self, other: &Self) -> Option<Self> {
+        let mut result = *self;
+        for i in 0..20 {
+            let shared = self.mask[i] & other.mask[i];
+            if self.value[i] & shared != other.value[i] & shared {
+                return None;
+            }
+            result.mask[i] |= other.mask[i];
+            result.value[i] |= other.value[i];
+        }
+        Some(result)
+    }
+
+    fn set_nibble(&mut self, position: usize, nibble: u8) {
+        let shift = if position.is_multiple_of(2) { 4 } else { 0 };
+        self.value[position / 2] |= nibble << shift;
+        self.mask[position / 2] |= 0x0f << shift;
+    }
+}
+
+/// Score of an address for the score-based pattern modes, `0` otherwise.
+///
+/// Every zero byte saves calldata gas whenever the address is passed around.
+/// [`PatternMode::PatternLength`] scores against the prefix in `address_mask`.
+pub fn address_score(pattern_mode: PatternMode, address_mask: &AddressMask, address: &[u8]) -> u32 {
+    match pattern_mode {
+        PatternMode::PatternLength => address_mask.prefix_score(address),
+        PatternMode::LeadingZeroBytes => {
+            address.iter().take_while(|byte| **byte == 0).count() as u32
+        }
+        PatternMode::TotalZeroBytes => address.iter().filter(|byte| **byte == 0).count() as u32,
+        _ => 0,
+    }
+}
+
+/// Re-checks a case-insensitive match against the EIP-55 checksum casing of `address`.
+///
+/// Only hex letters in the pattern are compared; digits and mask wildcards match either way.
+/// Returns the score to report, or `None` if the casing doesn't match. In
+/// [`PatternMode::PatternLength`] the score becomes the number of leading nibbles that also
+/// match in case.
+pub fn checksum_score(
+    pattern_mode: PatternMode,
+    pattern: &str,
+    suffix: &str,
+    address: &Address,
+    score: u32,
+) -> Option<u32> {
+    let checksummed = address.to_checksum(None);
+    let checksummed = &checksummed[2..];
+    let matched = match pattern_mode {
+        PatternMode::Prefix | PatternMode::Mask => same_case(checksummed, pattern),
+        PatternMode::Suffix => same_case(&checksummed[40 - pattern.len()..], pattern),
+        PatternMode::PrefixSuffix => {
+            same_case(checksummed, pattern) && same_case(&checksummed[40 - suffix.len()..], suffix)
+        }
+        PatternMode::Contains => (0..=40 - pattern.len()).any(|start| {
+            let window = &checksummed[start..start + pattern.len()];
+            window.eq_ignore_ascii_case(pattern) && same_case(window, pattern)
+        }),
+        PatternMode::PatternLength => {
+            let cased = checksummed
+                .chars()
+                .zip(pattern.chars())
+                .take_while(|(c, p)| c == p)
+                .count() as u32;
+            return Some(cased.min(score));
+        }
+        PatternMode::LeadingZeroBytes | PatternMode::TotalZeroBytes => true,
+    };
+    matched.then_some(score)
+}
+
+fn contains_nibbles(address: &[u8], nibbles: &[u8]) -> bool {
+    let nibble = |position: usize| {
+        let byte = address[position / 2];
+        if position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0f
+        }
+    };
+    (0..=40 - nibbles.len())
+        .any(|start| (0..nibbles.len()).all(|i| nibble(start + i) == nibbles[i]))
+}
+
+fn same_case(checksummed: &str, pattern: &str) -> bool {
+    checksummed
+        .chars()
+        .zip(pattern.chars())
+        .all(|(c, p)| !matches!(p, 'a'..='f' | 'A'..='F') || c == p)
+}
+
+pub(crate) fn strip_hex_prefix(value: &str) -> &str {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value)
+}
+
+pub(crate) fn parse_nibbles(pattern: &str) -> Result<Vec<u8>, &'static str> {
+    let pattern = strip_hex_prefix(pattern);
+    if pattern.len() > 40 {
+        return Err("pattern is longer than an address (40 nibbles)");
+    }
+    pattern
+        .chars()
+        .map(|c| c.to_digit(16).map(|n| n as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or("pattern must only contain hex digits")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nibbles_mask(bytes: &[(usize, u8, u8)]) -> AddressMask {
+        let mut mask = AddressMask::default();
+        for &(i, byte, byte_mask) in bytes {
+            mask.value[i] = byte;
+            mask.mask[i] = byte_mask;
+        }
+        mask
+    }
+
+    #[test]
+    fn prefix_constrains_the_leading_nibbles() {
+        assert_eq!(
+            AddressMask::prefix("dead"),
+            Ok(nibbles_mask(&[(0, 0xde, 0xff), (1, 0xad, 0xff)]))
+        );
+        assert_eq!(
+            AddressMask::prefix("0xabc"),
+            Ok(nibbles_mask(&[(0, 0xab, 0xff), (1, 0xc0, 0xf0)]))
+        );
+        assert_eq!(
+            AddressMask::prefix("0Xabc"),
+            AddressMask::prefix("0xabc"),
+            "either case of `0x` is stripped"
+        );
+        assert_eq!(
+            AddressMask::prefix("DEAD"),
+            AddressMask::prefix("dead"),
+            "masks ignore letter case"
+        );
+    }
+
+    #[test]
+    fn suffix_constrains_the_trailing_nibbles() {
+        assert_eq!(
+            AddressMask::suffix("beef"),
+            Ok(nibbles_mask(&[(18, 0xbe, 0xff), (19, 0xef, 0xff)]))
+        );
+        assert_eq!(
+            AddressMask::suffix("abc"),
+            Ok(nibbles_mask(&[(18, 0x0a, 0x0f), (19, 0xbc, 0xff)]))
+        );
+    }
+
+    #[test]
+    fn prefix_and_suffix_combine_unless_they_conflict() {
+        let both = AddressMask::from_pattern(PatternMode::PrefixSuffix, "dead", "beef").unwrap();
+        assert_eq!(
+            both,
+            nibbles_mask(&[
+                (0, 0xde, 0xff),
+                (1, 0xad, 0xff),
+                (18, 0xbe, 0xff),
+                (19, 0xef, 0xff)
+            ])
+        );
+
+        let prefix = "0".repeat(38);
+        assert!(AddressMask::from_pattern(PatternMode::PrefixSuffix, &prefix, "000").is_ok());
+        assert!(AddressMask::from_pattern(PatternMode::PrefixSuffix, &prefix, "100").is_err());
+    }
+
+    #[test]
+    fn parse_reads_forty_nibbles_with_wildcards() {
+        let pattern = "00XX00xx????????????????????????????dead";
+        let expected = nibbles_mask(&[
+            (0, 0x00, 0xff),
+            (2, 0x00, 0xff),
+            (18, 0xde, 0xff),
+            (19, 0xad, 0xff),
+        ]);
+        assert_eq!(AddressMask::parse(pattern), Ok(expected));
+        assert_eq!(AddressMask::parse(&format!("0x{}", pattern)), Ok(expected));
+        assert_eq!(AddressMask::parse(&format!("0X{}", pattern)), Ok(expected));
+        assert!(AddressMask::parse("00XX").is_err());
+        assert!(AddressMask::parse(&"g".repeat(40)).is_err());
+    }
+
+    #[test]
+    fn parse_line_skips_blank_and_comment_lines() {
+        assert_eq!(Pattern::parse_line(""), None);
+        assert_eq!(Pattern::parse_line("   \t"), None);
+        assert_eq!(Pattern::parse_line("# prefix dead"), None);
+        assert_eq!(
+            Pattern::parse_line("  dead  # trailing comment"),
+            Some(Pattern::new(PatternMode::Prefix, "dead", "", 0))
+        );
+    }
+
+    #[test]
+    fn parse_line_reads_every_mode_keyword() {
+        let mask = "00XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXdead";
+        for (line, expected) in [
+            ("dead", Pattern::new(PatternMode::Prefix, "dead", "", 0)),
+            (
+                "prefix 0xdead",
+                Pattern::new(PatternMode::Prefix, "dead", "", 0),
+            ),
+            (
+                "prefix 0XDEAD",
+                Pattern::new(PatternMode::Prefix, "DEAD", "", 0),
+            ),
+            (
+                "suffix beef",
+                Pattern::new(PatternMode::Suffix, "beef", "", 0),
+            ),
+            (
+                "prefix dead suffix beef",
+                Pattern::new(PatternMode::PrefixSuffix, "dead", "beef", 0),
+            ),
+            (
+                "contains cafe",
+                Pattern::new(PatternMode::Contains, "cafe", "", 0),
+            ),
+            (
+                &format!("mask {mask}"),
+                Pattern::new(PatternMode::Mask, mask, "", 0),
+            ),
+            (
+                "leading-zero-bytes 2",
+                Pattern::new(PatternMode::LeadingZeroBytes, "", "", 2),
+            ),
+            (
+                "total-zero-bytes 5",
+                Pattern::new(PatternMode::TotalZeroBytes, "", "", 5),
+            ),
+        ] {
+            let parsed = Pattern::parse_line(line).unwrap();
+            assert!(parsed.is_ok(), "{line:?}");
+            assert_eq!(parsed, expected, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn parse_line_rejects_invalid_lines() {
+        for line in [
+            "prefix",
+            "suffix dead beef",
+            "contains",
+            "unknown dead",
+            "prefix dead suffix",
+            "leading-zero-bytes",
+            "leading-zero-bytes many",
+            "leading-zero-bytes 0",
+            "total-zero-bytes 21",
+            "prefix xyz",
+            "mask 00XX",
+        ] {
+            assert!(
+                matches!(Pattern::parse_line(line), Some(Err(_))),
+                "{line:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn masks_match_and_score_addresses() {
+        let mut address = [0x11u8; 20];
+        address[0] = 0xde;
+        address[1] = 0xa0;
+        let mask = AddressMask::prefix("dead").unwrap();
+        assert!(!mask.matches(&address));
+        assert_eq!(mask.prefix_score(&address), 3);
+
+        address[1] = 0xad;
+        assert!(mask.matches(&address));
+        assert_eq!(mask.prefix_score(&address), 4);
+        assert!(AddressMask::default().matches(&address));
+    }
+
+    /// An address from EIP-55, checksummed `0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed`.
+    fn eip55_address() -> Address {
+        "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn checksum_score_compares_the_letters_of_textual_patterns() {
+        let address = eip55_address();
+        let score = |mode, pattern, suffix| checksum_score(mode, pattern, suffix, &address, 0);
+        assert_eq!(score(PatternMode::Prefix, "5aAeb", ""), Some(0));
+        assert_eq!(score(PatternMode::Prefix, "5aaeb", ""), None);
+        assert_eq!(
+            score(PatternMode::Prefix, "5", ""),
+            Some(0),
+            "digits have no case"
+        );
+        assert_eq!(score(PatternMode::Suffix, "BeAed", ""), Some(0));
+        assert_eq!(score(PatternMode::Suffix, "beaed", ""), None);
+        assert_eq!(score(PatternMode::PrefixSuffix, "5aA", "BeAed"), Some(0));
+        assert_eq!(score(PatternMode::PrefixSuffix, "5aA", "bEAed"), None);
+        assert_eq!(score(PatternMode::Contains, "F3E94C9", ""), Some(0));
+        assert_eq!(score(PatternMode::Contains, "f3e94c9", ""), None);
+        let mask = "5aAXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX";
+        assert_eq!(score(PatternMode::Mask, mask, ""), Some(0));
+        assert_eq!(score(PatternMode::Mask, &mask.to_lowercase(), ""), None);
+    }
+
+    #[test]
+    fn checksum_score_counts_cased_nibbles_for_pattern_length() {
+        let address = eip55_address();
+        let score = |pattern, score| {
+            checksum_score(PatternMode::PatternLength, pattern, "", &address, score)
+        };
+        // `5aAeb6` matches case-insensitively for 6 nibbles, in case for the first 3
+        assert_eq!(score("5aAEb6", 6), Some(3));
+        assert_eq!(score("5aAeb6", 6), Some(6));
+        assert_eq!(
+            score("5aAeb6", 4),
+            Some(4),
+            "never more than the case-insensitive score"
+        );
+    }
+
+    #[test]
+    fn checksum_score_keeps_zero_byte_scores() {
+        let address = eip55_address();
+        for mode in [PatternMode::LeadingZeroBytes, PatternMode::TotalZeroBytes] {
+            assert_eq!(checksum_score(mode, "", "", &address, 7), Some(7));
+        }
+    }
+}
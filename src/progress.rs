@@ -0,0 +1,372 @@
+//! Shared run state: speed reporting, stop conditions and the final summary.
+
+use alloy_primitives::{Address, B256};
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::Config;
+use crate::output::Found;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(2);
+const TICK: Duration = Duration::from_millis(100);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks any running search to stop, leaving its current batch part-way, and every later one
+/// to stop before it starts. Meant to be called from a signal handler.
+///
+/// A batch left part-way is only recorded as scanned up to its last reported result, so a
+/// run resumed from its checkpoint scans the rest of it again.
+///
+/// Returns `true` if a stop had already been requested.
+pub fn interrupt() -> bool {
+    INTERRUPTED.swap(true, Ordering::Relaxed)
+}
+
+/// Stops a [`Searcher`](crate::Searcher) run from another thread.
+///
+/// Clones share one flag, and a cancelled handle stops every later run it is used for too.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the run to stop, cutting its current batch short.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Salts checked by one part of a search, such as one of several devices, shown in the speed
+/// line next to the overall speed.
+#[derive(Clone, Debug)]
+pub struct Meter {
+    pub name: String,
+    pub checked: Arc<AtomicU64>,
+}
+
+impl Meter {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            checked: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+/// Why a search stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    MaxResults,
+    Timeout,
+    MaxAttempts,
+    Interrupted,        // SIGINT or SIGTERM, see [`interrupt`]
+    CollectionComplete, // Every token of the collection has a result
+    Cancelled,          // A [`CancelHandle`] or the caller's callback asked to stop
+}
+
+/// The highest-scoring result of a run, or its first result in pattern modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BestMatch {
+    pub salt: B256,
+    pub address: Address,
+    pub score: u32,
+}
+
+/// Statistics returned by [`search`](crate::search()) once a search stops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RunSummary {
+    pub attempts: u64,
+    pub elapsed: Duration,
+    pub results: u64,
+    pub best: Option<BestMatch>,
+    pub stop_reason: StopReason,
+}
+
+impl RunSummary {
+    /// Average salts checked per second.
+    pub fn rate(&self) -> f64 {
+        self.attempts as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.stop_reason {
+            StopReason::MaxResults => "reached --max-results",
+            StopReason::Timeout => "reached --timeout",
+            StopReason::MaxAttempts => "reached --max-attempts",
+            StopReason::Interrupted => "interrupted",
+            StopReason::CollectionComplete => "every token has a result",
+            StopReason::Cancelled => "cancelled",
+        };
+        writeln!(f, "🏁 Stopped: {}", reason)?;
+        writeln!(
+            f,
+            "   Checked: {} in {:.1}s ({:.2}M/s avg)",
+            self.attempts,
+            self.elapsed.as_secs_f64(),
+            self.rate() / 1_000_000.0
+        )?;
+        write!(f, "   Found:   {}", self.results)?;
+        if let Some(best) = &self.best {
+            write!(f, "\n   Best:    {}", best.address)?;
+            if best.score > 0 {
+                write!(f, " (score: {})", best.score)?;
+            }
+            write!(f, "\n   Salt:    {}", best.salt)?;
+        }
+        Ok(())
+    }
+}
+
+/// The thread started by [`Progress::spawn_reporter`]. Dropping it stops the run if nothing
+/// else has, e.g. when the search fails, and waits for the thread to end.
+pub(crate) struct Reporter {
+    progress: Arc<Progress>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        self.progress.stop(StopReason::Cancelled);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Counters shared by all workers of a run, plus the limits that end it.
+pub(crate) struct Progress {
+    checked: AtomicU64,
+    found: AtomicU64,
+    best: Mutex<Option<BestMatch>>,
+    stop_reason: OnceLock<StopReason>,
+    cancel: CancelHandle,
+    start: Instant,
+    max_results: Option<u64>,
+    timeout: Option<Duration>,
+    max_attempts: Option<u64>,
+}
+
+impl Progress {
+    pub fn new(config: &Config, cancel: CancelHandle) -> Arc<Self> {
+        Arc::new(Self {
+            checked: AtomicU64::new(0),
+            found: AtomicU64::new(0),
+            best: Mutex::new(None),
+            stop_reason: OnceLock::new(),
+            cancel,
+            start: Instant::now(),
+            max_results: config.max_results,
+            timeout: config.timeout,
+            max_attempts: config.max_attempts,
+        })
+    }
+
+    /// Asks every worker to stop. The first reason given is the one reported.
+    pub fn stop(&self, reason: StopReason) {
+        let _ = self.stop_reason.set(reason);
+    }
+
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        self.stop_reason.get().is_some()
+    }
+
+    /// Whether `--timeout`, an interrupt or a cancel stopped the run, which ends batches early.
+    #[inline]
+    pub fn is_cut_short(&self) -> bool {
+        matches!(
+            self.stop_reason.get(),
+            Some(StopReason::Timeout | StopReason::Interrupted | StopReason::Cancelled)
+        )
+    }
+
+    /// Claims up to `count` attempts from the `--max-attempts` budget, returning how many
+    /// may be checked. Stops the run once the budget is used up, when nothing is left to grant.
+    ///
+    /// Only the search driver claims attempts, one batch at a time, so the budget check doesn't
+    /// race.
+    pub fn claim_attempts(&self, count: u64) -> u64 {
+        let checked = self.checked.load(Ordering::Relaxed);
+        let granted = self
+            .max_attempts
+            .map_or(count, |max| max.saturating_sub(checked).min(count));
+        if granted == 0 {
+            self.stop(StopReason::MaxAttempts);
+        }
+        granted
+    }
+
+    /// Counts the salts a finished batch checked.
+    pub fn add_checked(&self, count: u64) {
+        self.checked.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Counts a result, or returns `false` if `--max-results` has already been reached and it
+    /// should be dropped. Stops the run when the last allowed result is counted.
+    pub fn claim_result(&self) -> bool {
+        let previous = self.found.fetch_add(1, Ordering::Relaxed);
+        let Some(max) = self.max_results else {
+            return true;
+        };
+        if previous + 1 >= max {
+            self.stop(StopReason::MaxResults);
+        }
+        previous < max
+    }
+
+    /// Keeps `found` as the best match if it scores higher than any before it.
+    pub fn offer_best(&self, found: &Found) {
+        let mut best = self.best.lock().unwrap();
+        if best.is_none_or(|best| found.score > best.score) {
+            *best = Some(BestMatch {
+                salt: found.salt,
+                address: found.address,
+                score: found.score,
+            });
+        }
+    }
+
+    /// Prints speed stats every couple of seconds, unless `label` is `None`, and enforces
+    /// `--timeout`, [`interrupt`] and [`CancelHandle::cancel`], until the run stops. Each of
+    /// `meters` gets its own speed at the end of the line.
+    pub fn spawn_reporter(
+        self: &Arc<Self>,
+        label: Option<&'static str>,
+        meters: Vec<Meter>,
+    ) -> Reporter {
+        let progress = Arc::clone(self);
+        let thread = std::thread::spawn(move || {
+            let mut last_count = 0u64;
+            let mut last_meters = vec![0u64; meters.len()];
+            let mut last_report = Instant::now();
+            while !progress.is_stopped() {
+                std::thread::sleep(TICK);
+                if INTERRUPTED.load(Ordering::Relaxed) {
+                    progress.stop(StopReason::Interrupted);
+                }
+                if progress.cancel.is_cancelled() {
+                    progress.stop(StopReason::Cancelled);
+                }
+                if progress
+                    .timeout
+                    .is_some_and(|timeout| progress.start.elapsed() >= timeout)
+                {
+                    progress.stop(StopReason::Timeout);
+                }
+                let Some(label) = label else {
+                    continue;
+                };
+                if last_report.elapsed() < REPORT_INTERVAL {
+                    continue;
+                }
+                let current = progress.attempts();
+                let found = progress.results();
+                let elapsed = progress.start.elapsed().as_secs_f64();
+                let speed = current as f64 / elapsed;
+                let interval = last_report.elapsed().as_secs_f64();
+                let instant_speed = (current - last_count) as f64 / interval;
+                let mut parts = String::new();
+                for (meter, last) in meters.iter().zip(&mut last_meters) {
+                    let checked = meter.checked.load(Ordering::Relaxed);
+                    let speed = (checked - *last) as f64 / interval;
+                    parts.push_str(&format!(" | {}: {:.2}M/s", meter.name, speed / 1_000_000.0));
+                    *last = checked;
+                }
+                last_count = current;
+                last_report = Instant::now();
+
+                eprint!(
+                    "\r{}: {:.2}M/s | Avg: {:.2}M/s | Checked: {}M | Found: {} | Time: {:.0}s{}    ",
+                    label,
+                    instant_speed / 1_000_000.0,
+                    speed / 1_000_000.0,
+                    current / 1_000_000,
+                    found,
+                    elapsed,
+                    parts
+                );
+                let _ = std::io::stderr().flush();
+            }
+            if label.is_some() {
+                eprintln!();
+            }
+        });
+        Reporter {
+            progress: Arc::clone(self),
+            thread: Some(thread),
+        }
+    }
+
+    /// Summary of a stopped run. Counters are capped at the limits that workers racing
+    /// past the stop may have overshot.
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            attempts: self.attempts(),
+            elapsed: self.start.elapsed(),
+            results: self.results(),
+            best: *self.best.lock().unwrap(),
+            stop_reason: *self
+                .stop_reason
+                .get()
+                .expect("summary requested before the run stopped"),
+        }
+    }
+
+    fn attempts(&self) -> u64 {
+        let checked = self.checked.load(Ordering::Relaxed);
+        self.max_attempts.map_or(checked, |max| checked.min(max))
+    }
+
+    fn results(&self) -> u64 {
+        let found = self.found.load(Ordering::Relaxed);
+        self.max_results.map_or(found, |max| found.min(max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropping_the_reporter_stops_it() {
+        let args = [
+            "erc6551crunch",
+            "-i",
+            "0x55266d75D1a14E4572138116aF39863Ed6596E7F",
+            "-c",
+            "1",
+            "-n",
+            "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D",
+            "-t",
+            "1",
+            "-p",
+            "dead",
+        ];
+        let config = Config::new(args.into_iter().map(String::from)).unwrap();
+        let progress = Progress::new(&config, CancelHandle::new());
+        let reporter = progress.spawn_reporter(None, Vec::new());
+
+        // Nothing else would ever stop this run
+        drop(reporter);
+        assert!(progress.is_stopped());
+        assert_eq!(
+            Arc::strong_count(&progress),
+            1,
+            "the reporter thread has ended"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Layout of the 32-byte salt: which bytes are fixed and which are searched.
+
+use alloy_primitives::{B256, hex};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::pattern::strip_hex_prefix;
+
+/// Bytes at the end of the searched range that hold the little-endian counter.
+pub const COUNTER_LEN: usize = 6;
+/// Fewest searched bytes: the counter and at least one segment byte.
+pub const MIN_SEARCHED_LEN: usize = COUNTER_LEN + 1;
+/// Most searched bytes: the counter and a full 6-byte segment.
+pub const MAX_SEARCHED_LEN: usize = COUNTER_LEN + 6;
+
+/// A salt with a contiguous range of searched bytes and every other byte fixed.
+///
+/// The searched range holds the segment bytes of a
+/// [`SearchPosition`](crate::SearchPosition), truncated to fit, followed by a 6-byte
+/// little-endian counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SaltTemplate {
+    fixed: [u8; 32], // searched bytes are zero
+    start: usize,
+    len: usize,
+}
+
+impl SaltTemplate {
+    /// Fixed bytes everywhere except `len` searched bytes starting at `start`.
+    pub fn new(fixed: [u8; 32], start: usize, len: usize) -> Result<Self, &'static str> {
+        if !(MIN_SEARCHED_LEN..=MAX_SEARCHED_LEN).contains(&len) {
+            return Err("a salt template must have 7 to 12 searched bytes");
+        }
+        if start + len > 32 {
+            return Err("searched salt bytes must fit in 32 bytes");
+        }
+        let mut fixed = fixed;
+        fixed[start..start + len].fill(0);
+        Ok(Self { fixed, start, len })
+    }
+
+    /// The default layout: the implementation address followed by 12 searched bytes.
+    pub fn implementation(implementation: [u8; 20]) -> Self {
+        let mut fixed = [0u8; 32];
+        fixed[..20].copy_from_slice(&implementation);
+        Self {
+            fixed,
+            start: 20,
+            len: MAX_SEARCHED_LEN,
+        }
+    }
+
+    /// `prefix` followed by up to 12 searched bytes, zero-padded to 32 bytes.
+    ///
+    /// Prefixes longer than 20 bytes leave fewer searched bytes, down to 7.
+    pub fn from_prefix(prefix: &str) -> Result<Self, &'static str> {
+        let prefix = hex::decode(strip_hex_prefix(prefix))
+            .map_err(|_| "--salt-prefix must be an even number of hex digits")?;
+        if prefix.len() > 32 - MIN_SEARCHED_LEN {
+            return Err("--salt-prefix must be at most 25 bytes");
+        }
+        let mut fixed = [0u8; 32];
+        fixed[..prefix.len()].copy_from_slice(&prefix);
+        let len = (32 - prefix.len()).min(MAX_SEARCHED_LEN);
+        Self::new(fixed, prefix.len(), len)
+    }
+
+    /// Salt bytes that are searched.
+    pub fn searched(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+
+    /// Position of the 6-byte little-endian counter within the salt.
+    pub fn counter_offset(&self) -> usize {
+        self.start + self.len - COUNTER_LEN
+    }
+
+    /// The salt for `counter` in the segment with the given bytes.
+    pub fn salt(&self, segment: &[u8; 6], counter: u64) -> B256 {
+        let mut salt = self.with_segment(segment);
+        let offset = self.counter_offset();
+        salt[offset..offset + COUNTER_LEN].copy_from_slice(&counter.to_le_bytes()[..COUNTER_LEN]);
+        salt.into()
+    }
+
+    /// The salt with the segment filled in and the counter bytes left zero.
+    pub fn with_segment(&self, segment: &[u8; 6]) -> [u8; 32] {
+        let mut salt = self.fixed;
+        let segment_len = self.len - COUNTER_LEN;
+        salt[self.start..self.start + segment_len].copy_from_slice(&segment[..segment_len]);
+        salt
+    }
+}
+
+impl FromStr for SaltTemplate {
+    type Err = &'static str;
+
+    /// Parses 64 hex digits with `XX` for every searched byte, e.g. 20 fixed bytes, 12 `XX`.
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let template = strip_hex_prefix(template);
+        if template.len() != 64 || !template.is_ascii() {
+            return Err("--salt-template must be 64 hex digits, with XX for searched bytes");
+        }
+        let mut fixed = [0u8; 32];
+        let mut searched = Vec::new();
+        for (i, byte) in template.as_bytes().chunks(2).enumerate() {
+            match byte {
+                b"XX" | b"xx" => searched.push(i),
+                _ if byte.iter().all(u8::is_ascii_hexdigit) => {
+                    fixed[i] = u8::from_str_radix(std::str::from_utf8(byte).unwrap(), 16).unwrap();
+                }
+                _ => return Err("--salt-template must use hex digits or XX for whole bytes"),
+            }
+        }
+        let (Some(&start), Some(&end)) = (searched.first(), searched.last()) else {
+            return Err("a salt template must have 7 to 12 searched bytes");
+        };
+        if end - start + 1 != searched.len() {
+            return Err("the searched bytes of a salt template must be contiguous");
+        }
+        Self::new(fixed, start, searched.len())
+    }
+}
+
+impl fmt::Display for SaltTemplate {
+    /// Formats the template as it would be given to `--salt-template`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x")?;
+        for (i, byte) in self.fixed.iter().enumerate() {
+            if self.searched().contains(&i) {
+                write!(f, "XX")?;
+            } else {
+                write!(f, "{:02x}", byte)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_parse_and_display_alike() {
+        let text = format!("0x{}{}{}", "ab".repeat(4), "XX".repeat(12), "cd".repeat(16));
+        let template: SaltTemplate = text.parse().unwrap();
+        assert_eq!(template.searched(), 4..16);
+        assert_eq!(template.counter_offset(), 10);
+        assert_eq!(template.to_string(), text);
+        assert_eq!(text[2..].parse(), Ok(template), "0x is optional");
+        assert_eq!(text.to_lowercase().parse(), Ok(template));
+    }
+
+    #[test]
+    fn invalid_templates_are_refused() {
+        let template = |fixed: usize, searched: usize| {
+            format!("{}{}", "00".repeat(fixed), "XX".repeat(searched)).parse::<SaltTemplate>()
+        };
+        assert!(template(26, 6).is_err(), "too few searched bytes");
+        assert!(template(19, 13).is_err(), "too many searched bytes");
+        assert!(template(25, 7).is_ok());
+        assert!(template(20, 12).is_ok());
+        assert!(template(30, 1).is_err(), "wrong length");
+        assert!(template(32, 0).is_err(), "nothing searched");
+
+        let gap = format!("{}00{}{}", "XX".repeat(4), "XX".repeat(4), "00".repeat(23));
+        assert!(
+            gap.parse::<SaltTemplate>().is_err(),
+            "searched bytes not contiguous"
+        );
+        let half = format!("X0{}{}", "XX".repeat(11), "00".repeat(20));
+        assert!(
+            half.parse::<SaltTemplate>().is_err(),
+            "half a searched byte"
+        );
+        let not_hex = format!("zz{}{}", "XX".repeat(12), "00".repeat(19));
+        assert!(not_hex.parse::<SaltTemplate>().is_err());
+    }
+
+    #[test]
+    fn prefixes_fix_the_leading_bytes() {
+        let template = SaltTemplate::from_prefix("0x0102").unwrap();
+        assert_eq!(template.searched(), 2..14);
+        let template = SaltTemplate::from_prefix(&"11".repeat(25)).unwrap();
+        assert_eq!(template.searched(), 25..32);
+        assert!(SaltTemplate::from_prefix(&"11".repeat(26)).is_err());
+        assert!(SaltTemplate::from_prefix("123").is_err());
+    }
+
+    #[test]
+    fn salts_hold_the_segment_then_the_little_endian_counter() {
+        let template = SaltTemplate::new([0xee; 32], 18, 8).unwrap();
+        assert_eq!(template.counter_offset(), 20);
+
+        let salt = template.salt(&[1, 2, 3, 4, 5, 6], 0x0a0b0c0d0e0f);
+        let mut expected = [0xee; 32];
+        expected[18..26].copy_from_slice(&[1, 2, 0x0f, 0x0e, 0x0d, 0x0c, 0x0b, 0x0a]);
+        assert_eq!(salt, B256::from(expected));
+
+        let mut base = expected;
+        base[20..26].fill(0);
+        assert_eq!(template.with_segment(&[1, 2, 3, 4, 5, 6]), base);
+    }
+
+    #[test]
+    fn the_default_layout_searches_after_the_implementation() {
+        let template = SaltTemplate::implementation([0x55; 20]);
+        assert_eq!(template.searched(), 20..32);
+        assert_eq!(template.counter_offset(), 26);
+        assert_eq!(
+            template.to_string(),
+            format!("0x{}{}", "55".repeat(20), "XX".repeat(12))
+        );
+    }
+}
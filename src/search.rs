@@ -0,0 +1,637 @@
+//! The search loop shared by every backend.
+//!
+//! A [`SearchBackend`] only hashes salts: it is handed one [`Batch`] of counters at a time and
+//! returns the [`Candidate`]s it found. [`search`] does everything around that — salt order and
+//! checkpoints, stop conditions, speed reports, optimize and collection bookkeeping, and
+//! writing results. [`Searcher`] runs the same loop without printing or writing anything,
+//! handing each [`Match`] to the caller instead.
+
+use alloy_primitives::{Address, B256};
+use std::error::Error;
+use std::ops::{ControlFlow, Range};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::JoinHandle;
+
+use crate::checkpoint::SaltCursor;
+use crate::output::{Found, ResultSink};
+use crate::progress::{CancelHandle, Meter, Progress, RunSummary, StopReason};
+use crate::{Config, Pattern, Target, first_match, print_pattern};
+
+/// Something that can hash batches of salts, such as the CPU or an OpenCL device.
+pub trait SearchBackend {
+    /// Name recorded with each result, e.g. `cpu`.
+    fn name(&self) -> &'static str;
+
+    /// Label of the live speed line, e.g. `⚡ Speed`.
+    fn speed_label(&self) -> &'static str;
+
+    /// Salts handed to each [`run_batch`](Self::run_batch) call. The last batch of a segment or
+    /// of the `--max-attempts` budget may be smaller.
+    fn batch_size(&self) -> u64;
+
+    /// Sets the backend up once, before the first batch. `targets` are the search targets that
+    /// [`Batch::targets`] and [`Candidate::target`] index into.
+    fn prepare(&mut self, config: &Config, targets: &[Target]) -> Result<(), Box<dyn Error>>;
+
+    /// Lines describing the prepared backend, printed by [`search`] before it starts.
+    fn describe(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Parts of the backend whose speeds are shown separately, e.g. one per device.
+    fn meters(&self) -> Vec<Meter> {
+        Vec::new()
+    }
+
+    /// Hashes every salt of `batch` against each of its targets and returns the addresses that
+    /// match one of `config`'s patterns.
+    ///
+    /// `stop` turns `true` when the run is cut short by `--timeout`, an interrupt or a cancel; a
+    /// backend
+    /// that checks it may return before the batch is done, with [`BatchOutcome::checked`]
+    /// telling how many salts it hashed.
+    fn run_batch(
+        &mut self,
+        config: &Config,
+        batch: &Batch,
+        stop: &(dyn Fn() -> bool + Sync),
+    ) -> Result<BatchOutcome, Box<dyn Error>>;
+}
+
+/// A contiguous range of counters within one segment of the salt sequence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Batch {
+    /// The salt with the segment filled in and the counter bytes left zero.
+    pub salt_base: [u8; 32],
+    /// Counters to hash, each written little-endian at the salt template's counter offset.
+    pub counters: Range<u64>,
+    /// Search targets checked for every salt.
+    pub targets: Range<usize>,
+    /// Lowest score worth reporting. Rises with every improvement in optimize mode.
+    pub min_score: u32,
+    /// Only the match with the lowest counter is used, as in collection mode, so salts past
+    /// the first match need not be hashed.
+    pub first_only: bool,
+}
+
+/// An address a backend found for one salt and target.
+///
+/// The driver matches it again on the host, so backends may report candidates that turn out
+/// not to match, e.g. ones that only match case-insensitively.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub counter: u64,
+    /// Index into the search targets.
+    pub target: usize,
+    pub address: Address,
+    pub init_code_hash: B256,
+}
+
+/// What a backend did with a [`Batch`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchOutcome {
+    /// Salts hashed, fewer than the batch holds if it was stopped or salts past the first
+    /// match were skipped.
+    pub checked: u64,
+    /// Candidates in any order.
+    pub candidates: Vec<Candidate>,
+    /// Matches that didn't fit the backend's result buffers at first and were found again by
+    /// hashing their salts once more. They are among `candidates` all the same.
+    pub overflowed: u64,
+}
+
+/// A salt found by a [`Searcher`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub salt: B256,
+    pub address: Address,
+    pub init_code_hash: B256,
+    /// Score of the matched pattern, see [`address_score`](crate::address_score).
+    pub score: u32,
+    /// The account the salt was found for.
+    pub target: Target,
+}
+
+/// Runs searches for library users, handing each [`Match`] to the caller.
+///
+/// Nothing is printed and no result file is written, so `output`, `no_file` and `format` in
+/// the [`Config`] are ignored. A `checkpoint` is still read and written.
+pub struct Searcher {
+    backend: Box<dyn SearchBackend + Send>,
+    cancel: CancelHandle,
+}
+
+impl Searcher {
+    pub fn new(backend: impl SearchBackend + Send + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+            cancel: CancelHandle::new(),
+        }
+    }
+
+    /// A handle that stops this searcher's runs from any thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Searches until a stop condition is met, the search is cancelled or `on_match` returns
+    /// [`ControlFlow::Break`], calling `on_match` with every result in salt order.
+    pub fn run(
+        &mut self,
+        config: &Config,
+        mut on_match: impl FnMut(Match) -> ControlFlow<()>,
+    ) -> Result<RunSummary, Box<dyn Error>> {
+        let search_targets = config.search_targets();
+        self.backend.prepare(config, &search_targets)?;
+        drive(
+            config,
+            self.backend.as_mut(),
+            &search_targets,
+            self.cancel.clone(),
+            None,
+            &mut |found| {
+                on_match(Match {
+                    salt: found.salt,
+                    address: found.address,
+                    init_code_hash: found.init_code_hash,
+                    score: found.score,
+                    target: *found.target,
+                })
+            },
+        )
+    }
+
+    /// Runs the search on its own thread, sending every result to the returned receiver.
+    ///
+    /// The search stops early if the receiver is dropped. Errors are returned as strings so
+    /// they can cross the thread boundary.
+    pub fn spawn(
+        mut self,
+        config: Config,
+    ) -> (Receiver<Match>, JoinHandle<Result<RunSummary, String>>) {
+        let (sender, receiver) = mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            self.run(&config, |found| match sender.send(found) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(_) => ControlFlow::Break(()),
+            })
+            .map_err(|err| err.to_string())
+        });
+        (receiver, handle)
+    }
+}
+
+/// Runs the search described by `config` on `backend` until a stop condition is met, printing
+/// progress and recording results like the command line does.
+pub fn search(
+    config: &Config,
+    backend: &mut dyn SearchBackend,
+) -> Result<RunSummary, Box<dyn Error>> {
+    let search_targets = config.search_targets();
+    backend.prepare(config, &search_targets)?;
+    for line in backend.describe() {
+        println!("{}", line);
+    }
+
+    let sink = ResultSink::new(config, backend.name())?;
+    let label = backend.speed_label();
+    let summary = drive(
+        config,
+        backend,
+        &search_targets,
+        CancelHandle::new(),
+        Some(label),
+        &mut |found| {
+            sink.record(found);
+            ControlFlow::Continue(())
+        },
+    )?;
+    sink.finish()?;
+    Ok(summary)
+}
+
+/// The loop behind [`search`] and [`Searcher`]. Prints nothing if `label` is `None`, and stops
+/// once `on_found` breaks.
+fn drive(
+    config: &Config,
+    backend: &mut dyn SearchBackend,
+    search_targets: &[Target],
+    cancel: CancelHandle,
+    label: Option<&'static str>,
+    on_found: &mut dyn FnMut(&Found) -> ControlFlow<()>,
+) -> Result<RunSummary, Box<dyn Error>> {
+    let collection = !config.collection.is_empty();
+
+    if label.is_some() {
+        print_pattern(config);
+        println!("🧂 Salt: {}", config.salt_template);
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    }
+
+    let mut cursor = SaltCursor::open(config, label.is_none())?;
+    let progress = Progress::new(config, cancel);
+    // Dropping the reporter stops and joins it, on errors as well
+    let reporter = progress.spawn_reporter(label, backend.meters());
+    // Best score reported so far in optimize mode, kept in the checkpoint
+    let mut best_score = cursor.best_score();
+
+    while !progress.is_stopped() {
+        let Some(step_targets) = config.step_targets(cursor.token()) else {
+            progress.stop(StopReason::CollectionComplete);
+            break;
+        };
+        // The last batch shrinks to whatever is left of the segment or --max-attempts budget
+        let (segment, mut counters) = cursor.next_batch(backend.batch_size());
+        let granted = progress.claim_attempts(counters.end - counters.start);
+        if granted == 0 {
+            break;
+        }
+        counters.end = counters.start + granted;
+
+        let batch = Batch {
+            salt_base: config.salt_template.with_segment(&segment),
+            counters: counters.clone(),
+            targets: step_targets,
+            // Optimize mode has a single pattern whose threshold rises with each improvement
+            min_score: if config.optimize {
+                (best_score + 1).max(config.patterns[0].min_score)
+            } else {
+                0
+            },
+            first_only: collection,
+        };
+        let outcome = backend.run_batch(config, &batch, &|| progress.is_cut_short())?;
+        progress.add_checked(outcome.checked);
+        if outcome.overflowed > 0 && label.is_some() {
+            eprint!(
+                "\n⚠️  {} matches overflowed the result buffer; their salts were hashed again so \
+                 none are lost\n",
+                outcome.overflowed
+            );
+        }
+
+        // Candidates are matched again to tag them with their pattern and re-check
+        // case-sensitive patterns against the EIP-55 checksum
+        let mut matches: Vec<(Candidate, &Pattern, u32)> = outcome
+            .candidates
+            .into_iter()
+            .filter_map(|candidate| {
+                let (pattern, score) = first_match(config, &candidate.address)?;
+                Some((candidate, pattern, score))
+            })
+            .collect();
+        // Backends report candidates in whatever order their workers finished; use salt order
+        matches.sort_unstable_by_key(|(candidate, ..)| (candidate.counter, candidate.target));
+
+        // In optimize mode only the batch's best result is reported, if it beats the best so far
+        if config.optimize {
+            let batch_best = matches.iter().map(|(.., score)| *score).max().unwrap_or(0);
+            matches.retain(|(.., score)| *score > best_score && *score == batch_best);
+            matches.truncate(1);
+        }
+
+        let mut batch_complete = outcome.checked == granted;
+        let mut token_found = false;
+        // Counter just past the last reported result, where a stopped batch resumes
+        let mut resume_at = None;
+        let mut matches = matches.into_iter().peekable();
+        while let Some((candidate, pattern, score)) = matches.next() {
+            if !progress.claim_result() {
+                batch_complete = false;
+                break;
+            }
+
+            let found = Found {
+                salt: config.salt_template.salt(&segment, candidate.counter),
+                address: candidate.address,
+                init_code_hash: candidate.init_code_hash,
+                target: &search_targets[candidate.target],
+                pattern,
+                score,
+            };
+            progress.offer_best(&found);
+            let flow = on_found(&found);
+            // A counter with more matching targets still to report is scanned again
+            resume_at = match matches.peek() {
+                Some((next, ..)) if next.counter == candidate.counter => Some(candidate.counter),
+                _ => Some(candidate.counter + 1),
+            };
+            if flow.is_break() {
+                progress.stop(StopReason::Cancelled);
+                token_found = collection;
+                batch_complete = false;
+                break;
+            }
+            // Only a score that was reported is the one to beat, here and after resuming
+            if config.optimize {
+                best_score = score;
+                cursor.set_best_score(best_score);
+            }
+
+            // In collection mode a token's first match in salt order is its result
+            if collection {
+                token_found = true;
+                break;
+            }
+        }
+
+        if token_found {
+            cursor.next_token()?;
+        } else if batch_complete {
+            cursor.complete(counters.end)?;
+        } else if let Some(end) = resume_at {
+            // A batch cut short by a stop, or whose results were dropped by --max-results, is
+            // resumed after its last reported result so none is reported twice
+            cursor.complete(end)?;
+        }
+    }
+
+    drop(reporter);
+    cursor.save()?;
+    Ok(progress.summary())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::salt::COUNTER_LEN;
+    use crate::{CpuBackend, TbaParams, compute_tba_address};
+    use alloy_primitives::U256;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// The CPU backend in batches of 1024 salts, so short runs span several batches.
+    struct SmallBatches(CpuBackend);
+
+    impl SearchBackend for SmallBatches {
+        fn name(&self) -> &'static str {
+            self.0.name()
+        }
+
+        fn speed_label(&self) -> &'static str {
+            self.0.speed_label()
+        }
+
+        fn batch_size(&self) -> u64 {
+            1024
+        }
+
+        fn prepare(&mut self, config: &Config, targets: &[Target]) -> Result<(), Box<dyn Error>> {
+            self.0.prepare(config, targets)
+        }
+
+        fn run_batch(
+            &mut self,
+            config: &Config,
+            batch: &Batch,
+            stop: &(dyn Fn() -> bool + Sync),
+        ) -> Result<BatchOutcome, Box<dyn Error>> {
+            self.0.run_batch(config, batch, stop)
+        }
+    }
+
+    /// A search for the usual test account with extra `args`.
+    fn config(args: &[&str]) -> Config {
+        let mut all = vec![
+            "erc6551crunch",
+            "-i",
+            "0x55266d75D1a14E4572138116aF39863Ed6596E7F",
+            "-c",
+            "1",
+            "-n",
+            "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D",
+            "-t",
+            "1",
+        ];
+        all.extend_from_slice(args);
+        Config::new(all.into_iter().map(String::from)).unwrap()
+    }
+
+    /// A checkpoint path named after `test`, with no file there yet.
+    fn checkpoint(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "erc6551crunch-search-{}-{}.ckpt",
+            test,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// Every match `backend` finds for `config`, and the run's summary.
+    fn run(
+        backend: impl SearchBackend + Send + 'static,
+        config: &Config,
+    ) -> (Vec<Match>, RunSummary) {
+        let mut matches = Vec::new();
+        let summary = Searcher::new(backend)
+            .run(config, |found| {
+                matches.push(found);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        (matches, summary)
+    }
+
+    #[test]
+    fn optimize_reports_strictly_better_scores_across_resumes() {
+        let path = checkpoint("optimize");
+        let config = config(&[
+            "-p",
+            "00000000",
+            "--optimize",
+            "pattern-length",
+            "--seed",
+            "5",
+            "--max-attempts",
+            "16384",
+            "--checkpoint",
+            path.to_str().unwrap(),
+        ]);
+
+        let (first, _) = run(SmallBatches(CpuBackend::new(0)), &config);
+        assert!(first.len() > 1, "expected several improvements: {first:?}");
+        assert!(
+            first.windows(2).all(|pair| pair[0].score < pair[1].score),
+            "{first:?}"
+        );
+
+        let best = first.last().unwrap().score;
+        let (resumed, _) = run(SmallBatches(CpuBackend::new(0)), &config);
+        assert!(
+            resumed.iter().all(|found| found.score > best),
+            "best was {best}: {resumed:?}"
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn max_attempts_stops_after_exactly_that_many_salts() {
+        let path = checkpoint("max-attempts");
+        let config = config(&[
+            "-p",
+            "dead",
+            "--seed",
+            "1",
+            "--max-attempts",
+            "3000",
+            "--checkpoint",
+            path.to_str().unwrap(),
+        ]);
+
+        let (matches, summary) = run(SmallBatches(CpuBackend::new(0)), &config);
+        assert_eq!(summary.stop_reason, StopReason::MaxAttempts);
+        assert_eq!(summary.attempts, 3000);
+        assert_eq!(summary.results, matches.len() as u64);
+        // The last batch shrank to what was left, and every salt was scanned
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\ncounter=3000\n"), "{saved}");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn max_results_stops_after_exactly_that_many_results() {
+        // One address in 16 starts with a zero nibble, so a batch has many more
+        let config = config(&[
+            "-p",
+            "0",
+            "--seed",
+            "1",
+            "--max-results",
+            "3",
+            "--max-attempts",
+            "1024",
+        ]);
+
+        let (matches, summary) = run(CpuBackend::new(0), &config);
+        assert_eq!(matches.len(), 3);
+        assert_eq!(summary.stop_reason, StopReason::MaxResults);
+        assert_eq!(summary.results, 3);
+        assert_eq!(summary.attempts, 1024);
+        assert_eq!(
+            summary.best.map(|best| best.salt),
+            Some(matches[0].salt),
+            "pattern modes keep the first result as the best"
+        );
+    }
+
+    #[test]
+    fn max_results_resumes_after_the_last_reported_result() {
+        let path = checkpoint("max-results");
+        let config = config(&[
+            "-p",
+            "0",
+            "--seed",
+            "9",
+            "--max-results",
+            "1",
+            "--max-attempts",
+            "4096",
+            "--checkpoint",
+            path.to_str().unwrap(),
+        ]);
+
+        let (first, _) = run(CpuBackend::new(0), &config);
+        let (second, _) = run(CpuBackend::new(0), &config);
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        assert_ne!(first[0].salt, second[0].salt);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn run_reports_matches_in_salt_order_at_their_addresses() {
+        let config = config(&["-p", "0", "--seed", "3", "--max-attempts", "4096"]);
+        let (matches, summary) = run(SmallBatches(CpuBackend::new(0)), &config);
+        assert!(matches.len() > 1, "{} matches", matches.len());
+        assert_eq!(summary.results, matches.len() as u64);
+
+        let offset = config.salt_template.counter_offset();
+        let counter = |found: &Match| {
+            let mut bytes = [0u8; 8];
+            bytes[..COUNTER_LEN].copy_from_slice(&found.salt[offset..offset + COUNTER_LEN]);
+            u64::from_le_bytes(bytes)
+        };
+        assert!(
+            matches
+                .windows(2)
+                .all(|pair| counter(&pair[0]) < counter(&pair[1]))
+        );
+
+        for found in &matches {
+            let params = TbaParams {
+                implementation: config.implement_address.into(),
+                chain_id: U256::from_be_bytes(found.target.chain_id),
+                token_contract: found.target.nft_address.into(),
+                token_id: U256::from_be_bytes(found.target.token_id),
+            };
+            assert_eq!(
+                found.address,
+                compute_tba_address(config.resistry_address.into(), &params, found.salt)
+            );
+        }
+    }
+
+    #[test]
+    fn run_stops_when_the_callback_breaks() {
+        let config = config(&["-p", "0", "--seed", "3", "--max-attempts", "65536"]);
+        let mut reported = 0;
+        let summary = Searcher::new(SmallBatches(CpuBackend::new(0)))
+            .run(&config, |_| {
+                reported += 1;
+                if reported == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(reported, 2);
+        assert_eq!(summary.stop_reason, StopReason::Cancelled);
+        assert_eq!(summary.results, 2);
+    }
+
+    #[test]
+    fn cancel_handle_stops_a_run_from_another_thread() {
+        // Never found, so only the cancel ends the run
+        let config = config(&["-p", "deadbeefdeadbeef"]);
+        let mut searcher = Searcher::new(CpuBackend::new(0));
+        let cancel = searcher.cancel_handle();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            cancel.cancel();
+        });
+
+        let summary = searcher
+            .run(&config, |_| ControlFlow::Continue(()))
+            .unwrap();
+        canceller.join().unwrap();
+        assert_eq!(summary.stop_reason, StopReason::Cancelled);
+        assert_eq!(summary.results, 0);
+    }
+
+    #[test]
+    fn spawn_sends_every_match() {
+        let config = config(&["-p", "0", "--seed", "3", "--max-attempts", "4096"]);
+        let (expected, _) = run(SmallBatches(CpuBackend::new(0)), &config);
+
+        let (receiver, handle) = Searcher::new(SmallBatches(CpuBackend::new(0))).spawn(config);
+        let received: Vec<Match> = receiver.iter().collect();
+        let summary = handle.join().unwrap().unwrap();
+        assert_eq!(summary.stop_reason, StopReason::MaxAttempts);
+        assert_eq!(summary.results, received.len() as u64);
+        assert_eq!(
+            received.iter().map(|found| found.salt).collect::<Vec<_>>(),
+            expected.iter().map(|found| found.salt).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn spawn_stops_once_the_receiver_is_dropped() {
+        let config = config(&["-p", "0", "--seed", "3"]);
+        let (receiver, handle) = Searcher::new(SmallBatches(CpuBackend::new(0))).spawn(config);
+        drop(receiver);
+        let summary = handle.join().unwrap().unwrap();
+        assert_eq!(summary.stop_reason, StopReason::Cancelled);
+    }
+}
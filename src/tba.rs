@@ -0,0 +1,63 @@
+//! Pure-Rust tokenbound account address derivation.
+//!
+//! Mirrors `computeAddressTBA` in `contracts/VanityCheckerERC6551.sol` and produces
+//! exactly the init code hashed by [`cpu`](crate::cpu) and the GPU kernel.
+
+use alloy_primitives::{Address, B256, U256, keccak256};
+
+use crate::{ERC6551_CONSTRUCTOR_HEADER, ERC6551_FOOTER};
+
+/// Length of the ERC6551 account init code in bytes (`0xb7`).
+pub const TBA_INIT_CODE_LEN: usize = 183;
+
+/// Everything that goes into an account's init code apart from the salt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TbaParams {
+    pub implementation: Address,
+    pub chain_id: U256,
+    pub token_contract: Address,
+    pub token_id: U256,
+}
+
+/// Builds the ERC6551 account init code (`getCreationCodeTBA`).
+///
+/// Layout: constructor header (20) + implementation (20) + footer (15) + salt (32)
+/// + chainId (32) + padded token contract (32) + tokenId (32).
+pub fn tba_init_code(params: &TbaParams, salt: B256) -> Vec<u8> {
+    let mut init_code = vec![0u8; TBA_INIT_CODE_LEN];
+    init_code[0..20].copy_from_slice(&ERC6551_CONSTRUCTOR_HEADER);
+    init_code[20..40].copy_from_slice(params.implementation.as_slice());
+    init_code[40..55].copy_from_slice(&ERC6551_FOOTER);
+    init_code[55..87].copy_from_slice(salt.as_slice());
+    init_code[87..119].copy_from_slice(&params.chain_id.to_be_bytes::<32>());
+    init_code[131..151].copy_from_slice(params.token_contract.as_slice());
+    init_code[151..].copy_from_slice(&params.token_id.to_be_bytes::<32>());
+    init_code
+}
+
+/// Keccak-256 of [`tba_init_code`].
+pub fn tba_init_code_hash(params: &TbaParams, salt: B256) -> B256 {
+    keccak256(tba_init_code(params, salt))
+}
+
+/// Computes the account address the registry deploys for `params` and `salt`.
+///
+/// ```
+/// use alloy_primitives::{U256, address, b256};
+/// use erc6551crunch::{ERC6551_REGISTRY, TbaParams, compute_tba_address};
+///
+/// let params = TbaParams {
+///     implementation: address!("55266d75D1a14E4572138116aF39863Ed6596E7F"),
+///     chain_id: U256::from(1),
+///     token_contract: address!("BC4CA0EdA7647A8aB7C2061c2E118A18a936f13D"),
+///     token_id: U256::from(1),
+/// };
+/// let salt = b256!("55266d75d1a14e4572138116af39863ed6596e7f70b12375dc5b3f0000000080");
+/// assert_eq!(
+///     compute_tba_address(ERC6551_REGISTRY.into(), &params, salt),
+///     address!("0009eb3ff14261a6a6461ff413464288342eb4e4"),
+/// );
+/// ```
+pub fn compute_tba_address(registry: Address, params: &TbaParams, salt: B256) -> Address {
+    registry.create2(salt, tba_init_code_hash(params, salt))
+}
@@ -0,0 +1,169 @@
+//! GPU launch shapes and the `--tune-cache` file that remembers the fastest one per device.
+//!
+//! A launch shape is how many salts each kernel launch hashes and how many work-items share a
+//! work group. Which shape keeps a device busy depends on the device, so
+//! [`OpenClBackend`](crate::OpenClBackend) times a few at startup and keeps the fastest.
+
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use crate::write_atomically;
+
+/// Salts per kernel launch and work-group size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct LaunchShape {
+    pub launch_size: usize,
+    /// `0` leaves the work-group size to the OpenCL driver.
+    pub local_size: usize,
+}
+
+impl fmt::Display for LaunchShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} salts per launch, local size ", self.launch_size)?;
+        match self.local_size {
+            0 => write!(f, "chosen by the driver"),
+            local_size => write!(f, "{}", local_size),
+        }
+    }
+}
+
+/// The launch shape cached for `device`, if the file has one.
+pub(crate) fn cached_shape(
+    path: &Path,
+    device: &str,
+) -> Result<Option<LaunchShape>, Box<dyn Error>> {
+    Ok(read_cache(path)?
+        .into_iter()
+        .find(|(name, _)| name == device)
+        .map(|(_, shape)| shape))
+}
+
+/// Records `shape` for `device`, keeping the other devices' entries. The file is replaced
+/// atomically.
+pub(crate) fn save_shape(
+    path: &Path,
+    device: &str,
+    shape: LaunchShape,
+) -> Result<(), Box<dyn Error>> {
+    let mut entries = read_cache(path)?;
+    entries.retain(|(name, _)| name != device);
+    entries.push((device.to_string(), shape));
+
+    let mut contents = String::from(
+        "# erc6551crunch GPU tuning: device=salts per launch,work-group size (0 for the driver's\n\
+         # choice)\n",
+    );
+    for (name, shape) in &entries {
+        contents += &format!("{}={},{}\n", name, shape.launch_size, shape.local_size);
+    }
+    write_atomically(path, &contents)
+        .map_err(|err| format!("Could not write tuning cache `{}`: {}", path.display(), err))?;
+    Ok(())
+}
+
+/// Every device's entry, none if the file doesn't exist yet.
+fn read_cache(path: &Path) -> Result<Vec<(String, LaunchShape)>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let invalid = || format!("`{}` is not a valid tuning cache", path.display());
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read tuning cache `{}`: {}", path.display(), err))?;
+
+    let mut entries = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Device names may contain `=`, the shape never does
+        let (name, shape) = line.rsplit_once('=').ok_or_else(invalid)?;
+        let (launch_size, local_size) = shape.split_once(',').ok_or_else(invalid)?;
+        let shape = LaunchShape {
+            launch_size: launch_size.parse().map_err(|_| invalid())?,
+            local_size: local_size.parse().map_err(|_| invalid())?,
+        };
+        if shape.launch_size == 0 {
+            return Err(invalid().into());
+        }
+        entries.push((name.to_string(), shape));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_path(test: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "erc6551crunch-{}-{}.tune",
+            test,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn saved_shapes_are_cached_per_device() {
+        let path = cache_path("round-trip");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(cached_shape(&path, "gpu a").unwrap(), None);
+
+        let a = LaunchShape {
+            launch_size: 1 << 20,
+            local_size: 128,
+        };
+        let b = LaunchShape {
+            launch_size: 1 << 22,
+            local_size: 0,
+        };
+        // Device names may contain `=`
+        save_shape(&path, "gpu a", a).unwrap();
+        save_shape(&path, "gpu=b", b).unwrap();
+        assert_eq!(cached_shape(&path, "gpu a").unwrap(), Some(a));
+        assert_eq!(cached_shape(&path, "gpu=b").unwrap(), Some(b));
+        assert_eq!(cached_shape(&path, "gpu c").unwrap(), None);
+
+        // Saving a device again replaces its entry only
+        save_shape(&path, "gpu a", b).unwrap();
+        assert_eq!(cached_shape(&path, "gpu a").unwrap(), Some(b));
+        assert_eq!(read_cache(&path).unwrap().len(), 2);
+
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        assert!(!Path::new(&temp_path).exists());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn corrupt_caches_are_refused() {
+        let path = cache_path("corrupt");
+        for contents in [
+            "gpu a\n",
+            "gpu a=1048576\n",
+            "gpu a=many,128\n",
+            "gpu a=1048576,-1\n",
+            "gpu a=0,128\n",
+        ] {
+            std::fs::write(&path, contents).unwrap();
+            let err = read_cache(&path).err().unwrap();
+            assert!(
+                err.to_string().contains("not a valid tuning cache"),
+                "{err}"
+            );
+            assert!(
+                save_shape(
+                    &path,
+                    "gpu b",
+                    LaunchShape {
+                        launch_size: 1,
+                        local_size: 0,
+                    }
+                )
+                .is_err()
+            );
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,207 @@
+use alloy_primitives::{Address, B256, U256, hex};
+use std::error::Error;
+
+use crate::tba::{TbaParams, tba_init_code_hash};
+use crate::{ERC6551_REGISTRY, parse_address, parse_chain_id, parse_token_id};
+
+pub struct VerifyConfig {
+    pub resistry_address: [u8; 20],
+    pub implement_address: [u8; 20],
+    pub chain_id: [u8; 32],
+    pub nft_address: [u8; 20],
+    pub token_id: [u8; 32],
+    pub salt: [u8; 32],
+    pub expected_address: Option<[u8; 20]>,
+}
+
+impl VerifyConfig {
+    /// Parses command line arguments, starting with the program name and `verify`.
+    pub fn new(mut args: impl Iterator<Item = String>) -> Result<Self, &'static str> {
+        args.next(); // skip program name
+        args.next(); // skip `verify` subcommand
+
+        let mut resistry_address_string: Option<String> = None;
+        let mut implement_address_string: Option<String> = None;
+        let mut chain_id_string: Option<String> = None;
+        let mut nft_address_string: Option<String> = None;
+        let mut token_id_string: Option<String> = None;
+        let mut salt_string: Option<String> = None;
+        let mut expected_address_string: Option<String> = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--registry" | "-r" => {
+                    resistry_address_string =
+                        Some(args.next().ok_or("--registry requires an address")?);
+                }
+                "--implementation" | "--impl" | "-i" => {
+                    implement_address_string =
+                        Some(args.next().ok_or("--implementation requires an address")?);
+                }
+                "--chain" | "-c" => {
+                    chain_id_string = Some(args.next().ok_or("--chain requires a chain ID")?);
+                }
+                "--nft" | "-n" => {
+                    nft_address_string = Some(args.next().ok_or("--nft requires an address")?);
+                }
+                "--token" | "-t" => {
+                    token_id_string = Some(args.next().ok_or("--token requires a token ID")?);
+                }
+                "--salt" | "-s" => {
+                    salt_string = Some(args.next().ok_or("--salt requires a 32-byte hex value")?);
+                }
+                "--expected" | "-e" => {
+                    expected_address_string =
+                        Some(args.next().ok_or("--expected requires an address")?);
+                }
+                _ => {
+                    return Err("Unknown argument. Use `verify --help` for usage.");
+                }
+            }
+        }
+
+        let implement_address_string =
+            implement_address_string.ok_or("Missing --implementation argument")?;
+        let chain_id_string = chain_id_string.ok_or("Missing --chain argument")?;
+        let nft_address_string = nft_address_string.ok_or("Missing --nft argument")?;
+        let token_id_string = token_id_string.ok_or("Missing --token argument")?;
+        let salt_string = salt_string.ok_or("Missing --salt argument")?;
+
+        let resistry_address = match resistry_address_string {
+            Some(address) => {
+                parse_address(&address).ok_or("could not decode registry address argument")?
+            }
+            None => ERC6551_REGISTRY,
+        };
+        let implement_address = parse_address(&implement_address_string)
+            .ok_or("could not decode implement address argument")?;
+        let nft_address =
+            parse_address(&nft_address_string).ok_or("could not decode nft address argument")?;
+        let chain_id = parse_chain_id(&chain_id_string)?;
+        let token_id = parse_token_id(&token_id_string)?;
+
+        let Ok(salt_vec) = hex::decode(salt_string) else {
+            return Err("could not decode salt argument");
+        };
+        let Ok(salt) = salt_vec.try_into() else {
+            return Err("invalid length for salt argument (expected 32 bytes)");
+        };
+
+        let expected_address = match expected_address_string {
+            Some(address) => {
+                Some(parse_address(&address).ok_or("could not decode expected address argument")?)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            resistry_address,
+            implement_address,
+            chain_id,
+            nft_address,
+            token_id,
+            salt,
+            expected_address,
+        })
+    }
+}
+
+/// Recomputes the tokenbound account address for a salt without any network access.
+///
+/// Returns `Ok(false)` when an expected address was given and does not match.
+pub fn verify(config: VerifyConfig) -> Result<bool, Box<dyn Error>> {
+    let params = TbaParams {
+        implementation: Address::from(config.implement_address),
+        chain_id: U256::from_be_bytes(config.chain_id),
+        token_contract: Address::from(config.nft_address),
+        token_id: U256::from_be_bytes(config.token_id),
+    };
+    let registry = Address::from(config.resistry_address);
+    let salt = B256::from(config.salt);
+    let init_code_hash = tba_init_code_hash(&params, salt);
+    let address = registry.create2(salt, init_code_hash);
+
+    println!("🔎 Salt:           {}", salt);
+    println!("   Registry:       {}", registry);
+    println!("   Init code hash: {}", init_code_hash);
+    println!("   Address:        {}", address);
+
+    let Some(expected) = config.expected_address else {
+        return Ok(true);
+    };
+    let expected = Address::from(expected);
+    if address == expected {
+        println!("✅ Address matches expected {}", expected);
+        Ok(true)
+    } else {
+        println!("❌ Address does not match expected {}", expected);
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example of `compute_tba_address`
+    const SALT: &str = "0x55266d75d1a14e4572138116af39863ed6596e7f70b12375dc5b3f0000000080";
+    const ADDRESS: &str = "0x0009eb3ff14261a6a6461ff413464288342eb4e4";
+
+    fn config(extra: &[&str]) -> Result<VerifyConfig, &'static str> {
+        let mut args = vec![
+            "erc6551crunch",
+            "verify",
+            "--salt",
+            SALT,
+            "-i",
+            "0x55266d75D1a14E4572138116aF39863Ed6596E7F",
+            "-c",
+            "1",
+            "-n",
+            "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D",
+            "-t",
+            "0x1",
+        ];
+        args.extend_from_slice(extra);
+        VerifyConfig::new(args.into_iter().map(String::from))
+    }
+
+    #[test]
+    fn new_parses_every_argument() {
+        let config = config(&["-e", ADDRESS]).unwrap();
+        assert_eq!(config.resistry_address, ERC6551_REGISTRY);
+        assert_eq!(config.chain_id, U256::from(1).to_be_bytes());
+        assert_eq!(config.token_id, U256::from(1).to_be_bytes());
+        assert_eq!(config.salt, SALT.parse::<B256>().unwrap().0);
+        assert_eq!(
+            config.expected_address,
+            Some(ADDRESS.parse::<Address>().unwrap().0.0)
+        );
+
+        let registry = "0x000000000000000000000000000000000000dEaD";
+        let config = self::config(&["-r", registry]).unwrap();
+        assert_eq!(config.resistry_address, parse_address(registry).unwrap());
+    }
+
+    #[test]
+    fn new_rejects_missing_and_malformed_arguments() {
+        let args = ["erc6551crunch", "verify", "--salt", SALT];
+        assert!(VerifyConfig::new(args.into_iter().map(String::from)).is_err());
+        assert!(config(&["--bogus"]).is_err());
+        assert!(config(&["-e", "0x1234"]).is_err());
+        assert!(config(&["-c", "1_000"]).is_err());
+        assert!(config(&["-s", "0x1234"]).is_err());
+    }
+
+    #[test]
+    fn verify_reports_whether_the_expected_address_matches() {
+        assert!(verify(config(&["-e", ADDRESS]).unwrap()).unwrap());
+        let other = "0x0009eb3ff14261a6a6461ff413464288342eb4e5";
+        assert!(!verify(config(&["-e", other]).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn verify_without_expected_address_succeeds() {
+        assert!(verify(config(&[]).unwrap()).unwrap());
+    }
+}
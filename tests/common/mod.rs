@@ -0,0 +1,46 @@
+//! Setup shared by the GPU tests, which need an OpenCL device and so are ignored by default.
+
+use erc6551crunch::{Config, Match, OpenClBackend, Searcher, select_devices};
+use std::ops::ControlFlow;
+
+/// A GPU search with `args` for the pattern and targets, checking 131072 salts in 64K-salt
+/// launches without tuning or writing a result file.
+pub fn gpu_config(test: &str, args: &[&str]) -> Config {
+    let mut all = vec![
+        test,
+        "-i",
+        "0x55266d75D1a14E4572138116aF39863Ed6596E7F",
+        "--gpu",
+        "--launch-size",
+        "65536",
+        "--local-size",
+        "0",
+        "--max-attempts",
+        "131072",
+        "--no-file",
+    ];
+    all.extend_from_slice(args);
+    Config::new(all.into_iter().map(String::from)).unwrap()
+}
+
+/// A backend for the default platform's first device.
+pub fn gpu_backend() -> OpenClBackend {
+    let (platform, device) = select_devices(None, &[])
+        .expect("no OpenCL platform")
+        .first()
+        .copied()
+        .expect("no OpenCL device");
+    OpenClBackend::new(platform, device)
+}
+
+/// Every match `searcher` finds for `config`, in salt order.
+pub fn matches(mut searcher: Searcher, config: &Config) -> Vec<Match> {
+    let mut matches = Vec::new();
+    searcher
+        .run(config, |found| {
+            matches.push(found);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+    matches
+}
@@ -0,0 +1,51 @@
+//! The GPU kernel finds exactly the salts the CPU does, for salt layouts that put the counter
+//! in different lanes and for several targets at once. Needs an OpenCL device, so it is
+//! ignored by default; run it with `cargo test -- --ignored`.
+
+mod common;
+
+use common::{gpu_backend, gpu_config, matches};
+use erc6551crunch::{CpuBackend, Searcher};
+
+#[test]
+#[ignore = "needs an OpenCL device"]
+fn gpu_matches_cpu_for_every_counter_offset() {
+    // Counter offsets 1, 7, 20 and 26, the last being the default layout
+    let layouts: [&[&str]; 4] = [
+        &[
+            "--salt-template",
+            &format!("{}{}", "XX".repeat(7), "ab".repeat(25)),
+        ],
+        &[
+            "--salt-template",
+            &format!("{}{}{}", "01".repeat(3), "XX".repeat(10), "cd".repeat(19)),
+        ],
+        &[
+            "--salt-template",
+            &format!("{}{}{}", "ef".repeat(14), "XX".repeat(12), "ef".repeat(6)),
+        ],
+        &[],
+    ];
+    for layout in layouts {
+        // One address in 256 starts with a zero byte, well within the result buffers
+        let mut args = vec![
+            "--target",
+            "1:BC4CA0EdA7647A8aB7C2061c2E118A18a936f13D:1",
+            "--target",
+            "8453:BC4CA0EdA7647A8aB7C2061c2E118A18a936f13D:7",
+            "--target",
+            "137:60E4d786628Fea6478F785A6d7e704777c86a7c6:123456789",
+            "-p",
+            "00",
+            "--seed",
+            "3",
+        ];
+        args.extend_from_slice(layout);
+        let config = gpu_config("gpu_equivalence", &args);
+
+        let gpu_matches = matches(Searcher::new(gpu_backend()), &config);
+        let cpu_matches = matches(Searcher::new(CpuBackend::new(0)), &config);
+        assert!(!cpu_matches.is_empty(), "expected matches for {layout:?}");
+        assert_eq!(gpu_matches, cpu_matches, "salt layout {layout:?}");
+    }
+}
@@ -0,0 +1,37 @@
+//! A GPU search for a pattern so easy that its launches overflow the result buffers still
+//! records every match. Needs an OpenCL device, so it is ignored by default; run it with
+//! `cargo test -- --ignored`.
+
+mod common;
+
+use common::{gpu_backend, gpu_config, matches};
+use erc6551crunch::{CpuBackend, Searcher};
+
+#[test]
+#[ignore = "needs an OpenCL device"]
+fn gpu_records_every_match_past_the_result_buffers() {
+    // One address in 16 starts with a zero nibble, about 4096 per 64K-salt launch against
+    // room for 1024
+    let args = [
+        "-c",
+        "1",
+        "-n",
+        "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D",
+        "-t",
+        "1",
+        "-p",
+        "0",
+        "--seed",
+        "1",
+    ];
+    let config = gpu_config("gpu_overflow", &args);
+
+    let gpu_matches = matches(Searcher::new(gpu_backend()), &config);
+    let cpu_matches = matches(Searcher::new(CpuBackend::new(0)), &config);
+    assert!(
+        gpu_matches.len() > 2 * 1024,
+        "expected the pattern to overflow the result buffers, found {} matches",
+        gpu_matches.len()
+    );
+    assert_eq!(gpu_matches, cpu_matches);
+}